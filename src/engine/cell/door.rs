@@ -1,5 +1,8 @@
+use std::collections::BTreeSet;
+
 use super::Direction;
 use super::{Interaction, Thin};
+use crate::engine::event::Event;
 
 #[derive(PartialEq)]
 enum DoorState {
@@ -15,26 +18,36 @@ pub struct Door {
     dir: Direction,
     delta: f32,
     state: DoorState,
+    /// ID of the key opening the door
+    lock: Option<String>,
 }
 
 impl Door {
-    pub fn new(value: u32, dir: Direction) -> Door {
+    pub fn new(value: u32, dir: Direction, lock: Option<String>) -> Door {
         Door {
             value,
             slide: 1.0,
             dir,
             delta: 0.0,
             state: DoorState::Closed,
+            lock,
         }
     }
 }
 
 impl Interaction for Door {
-    fn trigger(&mut self) {
-        if self.state == DoorState::Closed {
-            self.delta = 0.0;
-            self.state = DoorState::Opening
+    fn trigger(&mut self, keys: &BTreeSet<String>) -> Option<Event> {
+        if self.state != DoorState::Closed {
+            return None;
+        }
+        if let Some(key) = &self.lock {
+            if !keys.contains(key) {
+                return Some(Event::Locked { key: key.clone() });
+            }
         }
+        self.delta = 0.0;
+        self.state = DoorState::Opening;
+        None
     }
 
     fn update(&mut self, delta: f32) {
@@ -89,6 +102,10 @@ impl Thin for Door {
     fn pushable(&self) -> bool {
         false
     }
+
+    fn lock(&self) -> Option<&str> {
+        self.lock.as_deref()
+    }
 }
 
 
@@ -1,6 +1,11 @@
+use std::collections::BTreeSet;
+
 use door::{Door};
 use thinwall::ThinWall;
 use super::Direction;
+use super::event::Event;
+use super::schema::{CellData, MapError};
+use crate::graphics::TextureAtlas;
 
 mod door;
 mod thinwall;
@@ -8,7 +13,13 @@ mod thinwall;
 pub static DOOR_VALUE: u32 = 13;
 
 pub trait Interaction {
-    fn trigger(&mut self);
+    /// Open or push the cell
+    ///
+    /// # Arguments
+    /// * keys - Keys held by whoever triggers the cell, by ID
+    ///
+    /// Return the event of a refusal
+    fn trigger(&mut self, keys: &BTreeSet<String>) -> Option<Event>;
 
     fn update(&mut self, delta: f32);
 }
@@ -23,35 +34,92 @@ pub trait Thin: Interaction {
     fn depth(&self) -> f32;
 
     fn pushable(&self) -> bool;
+
+    /// ID of the key opening the cell, `None` when anyone may
+    fn lock(&self) -> Option<&str>;
 }
 
+#[derive(Default)]
 pub enum Cell {
+    #[default]
     Empty,
     Wall { value: u32, height: f32 },
     Thin(Box<dyn Thin>),
 }
 
 impl Cell {
-    pub fn from_json(json: &serde_json::Value) -> Cell {
-        let cell = json.as_object().unwrap();
-        let value = cell["value"].as_u64().unwrap() as u32;
-
-        if cell.contains_key("thin") {
-            let dir = Direction::from_str(cell["direction"].as_str().unwrap());
-            let pushable = cell["pushable"].as_bool().unwrap();
+    /// Build a cell from its description
+    ///
+    /// # Arguments
+    /// * data - Cell description
+    /// * path - JSON path of the description, used in errors
+    /// * atlas - Textures the value must be bound to
+    ///
+    /// Return the cell or the error
+    pub fn from_data(data: &CellData, path: &str, atlas: &TextureAtlas) -> Result<Cell, MapError> {
+        let value = match data.value {
+            Some(value) => value,
+            None if data.thin.is_none() && data.height.is_none() && !data.pushable => return Ok(Cell::Empty),
+            None => return Err(MapError::new(format!("{}.value", path), "walls, thin walls and doors need a value")),
+        };
+
+        if !atlas.has_wall(value) {
+            return Err(MapError::new(format!("{}.value", path), format!("no texture is bound to {}", value)));
+        }
+        if data.lock.is_some() && (data.thin.is_none() || value < DOOR_VALUE) {
+            return Err(MapError::new(format!("{}.lock", path), "only doors can be locked"));
+        }
+        if data.pushable {
+            if data.thin.is_some() && value >= DOOR_VALUE {
+                return Err(MapError::new(format!("{}.pushable", path), "doors cannot be pushed"));
+            }
+            if data.height.is_some_and(|height| height != 1.0) {
+                return Err(MapError::new(format!("{}.height", path), "pushwalls are a full cell high"));
+            }
+            //Resting pushwalls are plain blocks, whatever their direction
+            return Ok(Cell::Wall { value, height: 1.0 });
+        }
+        if data.thin.is_some() {
+            let dir = match data.direction {
+                Some(side) => Direction::from(side),
+                None => return Err(MapError::new(format!("{}.direction", path), "thin walls and doors need a direction")),
+            };
 
             if value >= DOOR_VALUE {
-                Cell::Thin(Box::new(Door::new(value, dir)))
+                Ok(Cell::Thin(Box::new(Door::new(value, dir, data.lock.clone()))))
             } else {
-                Cell::Thin(Box::new(ThinWall::new(value, dir, pushable)))
+                Ok(Cell::Thin(Box::new(ThinWall::new(value, dir, false))))
             }
         } else {
-            let height = cell["height"].as_f64().unwrap() as f32;
+            let height = match data.height {
+                Some(height) if height > 0.0 && height <= 1.0 => height,
+                Some(height) => return Err(MapError::new(format!("{}.height", path), format!("height must be in ]0, 1], got {}", height))),
+                None => return Err(MapError::new(format!("{}.height", path), "walls need a height")),
+            };
 
-            Cell::Wall { value, height }
+            Ok(Cell::Wall { value, height })
         }
     }
 
+    /// Pushwall leaving its cell, drawn by its back face
+    ///
+    /// # Arguments
+    /// * value - Wall texture
+    /// * dir - Direction of the slide
+    pub fn pushed(value: u32, dir: Direction) -> Cell {
+        let face = match dir {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+            dir => dir,
+        };
+        let mut wall = ThinWall::new(value, face, true);
+
+        wall.trigger(&BTreeSet::new());
+        Cell::Thin(Box::new(wall))
+    }
+
     pub fn value(&self) -> u32 {
         match self {
             Cell::Empty => 0,
@@ -70,9 +138,10 @@ impl Cell {
 }
 
 impl Interaction for Cell {
-    fn trigger(&mut self) {
-        if let Cell::Thin(thin) = self {
-            thin.trigger();
+    fn trigger(&mut self, keys: &BTreeSet<String>) -> Option<Event> {
+        match self {
+            Cell::Thin(thin) => thin.trigger(keys),
+            _ => None,
         }
     }
 
@@ -84,10 +153,4 @@ impl Interaction for Cell {
             }
         }
     }
-}
-
-impl Default for Cell {
-    fn default() -> Cell {
-        Cell::Empty
-    }
 }
\ No newline at end of file
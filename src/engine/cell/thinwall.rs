@@ -1,5 +1,8 @@
+use std::collections::BTreeSet;
+
 use super::Direction;
 use super::{Interaction, Thin};
+use crate::engine::event::Event;
 
 #[derive(Clone)]
 pub struct ThinWall {
@@ -27,11 +30,12 @@ impl ThinWall {
 }
 
 impl Interaction for ThinWall {
-    fn trigger(&mut self) {
+    fn trigger(&mut self, _keys: &BTreeSet<String>) -> Option<Event> {
         if self.pushable {
             self.moving = true;
             self.delta = 2.0 * (1.0 - self.depth);
         }
+        None
     }
 
     fn update(&mut self, delta: f32) {
@@ -68,4 +72,8 @@ impl Thin for ThinWall {
     fn pushable(&self) -> bool {
         self.pushable
     }
+
+    fn lock(&self) -> Option<&str> {
+        None
+    }
 }
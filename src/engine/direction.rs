@@ -1,5 +1,6 @@
-#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Default)]
 pub enum Direction {
+    #[default]
     None,
     North,
     West,
@@ -10,16 +11,6 @@ pub enum Direction {
 }
 
 impl Direction {
-    pub fn from_str(str: &str) -> Direction {
-        match str {
-            "North" | "North/South" => Direction::North,
-            "West" | "East/West" => Direction::West,
-            "South" => Direction::South,
-            "East" => Direction::East,
-            _ => Direction::None
-        }
-    }
-
     pub fn is_side(&self) -> bool {
         *self < Direction::Up
     }
@@ -32,7 +23,7 @@ impl Direction {
         }
     }
 
-    pub fn to_degree(&self) -> f32 {
+    pub fn to_degree(self) -> f32 {
         match self {
             Direction::North => 270.0,
             Direction::West => 360.0,
@@ -42,9 +33,3 @@ impl Direction {
         }
     }
 }
-
-impl Default for Direction {
-    fn default() -> Direction {
-        Direction::None
-    }
-}
\ No newline at end of file
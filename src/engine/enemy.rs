@@ -0,0 +1,498 @@
+use std::collections::BTreeSet;
+
+use super::map::Map;
+use super::cell::{Cell, Interaction, DOOR_VALUE};
+use super::direction::Direction;
+use super::event::Event;
+use super::navigation::{self, NavOptions, Waypoint};
+use super::player::Player;
+use super::rayobject::Ray;
+use super::sprite::Sprite;
+use super::vectors::{Vector2f, Vector3f};
+use super::schema::{EnemyData, MapError};
+
+/// Health of an enemy which does not give one
+const HEALTH: u32 = 25;
+/// Half of the width of an enemy, kept away from the walls
+const RADIUS: f32 = 0.3;
+/// Distance up to which the player is noticed
+const SIGHT_DIST: f32 = 12.0;
+/// Distance up to which the player is shot at
+const ATTACK_DIST: f32 = 6.0;
+/// Distance kept with the player while chasing
+const CLOSE_DIST: f32 = 0.8;
+const PATROL_SPEED: f32 = 1.0;
+const CHASE_SPEED: f32 = 2.0;
+/// Duration of an attack, from aiming to lowering the gun
+const ATTACK_TIME: f32 = 0.6;
+/// Time of the shot in an attack
+const FIRE_TIME: f32 = 0.3;
+/// Time between two attacks
+const ATTACK_COOLDOWN: f32 = 0.8;
+/// Damage of a shot at point-blank range, fading to half of it at the attack distance
+const SHOT_DAMAGE: f32 = 12.0;
+const PAIN_TIME: f32 = 0.25;
+const DEATH_TIME: f32 = 0.6;
+/// Duration of a walking frame
+const WALK_FRAME: f32 = 0.16;
+/// Time between two path searches
+const REPATH_TIME: f32 = 0.5;
+/// Time the player is tracked after getting out of sight
+const LOSE_TIME: f32 = 10.0;
+
+/// Rows of the enemy sheet, see [`TextureAtlas`](crate::graphics::TextureAtlas)
+const STAND_ROW: u32 = 0;
+const WALK_ROW: u32 = 1;
+const AIM_ROW: u32 = 5;
+const FIRE_ROW: u32 = 6;
+const PAIN_ROW: u32 = 7;
+const DEATH_ROW: u32 = 8;
+const DEATH_FRAMES: u32 = 4;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EnemyState {
+    /// Standing guard
+    Idle,
+    /// Walking along its patrol
+    Patrol,
+    /// Running after the player, then to where it was last seen for a while once out of sight
+    Chase,
+    /// Aiming and firing at the player
+    Attack,
+    /// Flinching after being hurt
+    Pain,
+    /// Falling
+    Death,
+    /// Lying on the ground
+    Dead,
+}
+
+///
+/// Enemy soldier
+///
+/// Guards or patrols until the player comes into sight, then chases and shoots it
+///
+pub struct Enemy {
+    pub pos: Vector3f,
+    /// Where the enemy looks, normalized
+    pub dir: Vector2f,
+    pub state: EnemyState,
+    pub health: u32,
+
+    id: usize,
+    patrol: Vec<Vector3f>,
+    waypoint: usize,
+    path: Vec<Waypoint>,
+    repath: f32,
+    last_seen: Vector3f,
+    unseen: f32,
+    timer: f32,
+    cooldown: f32,
+    walk: f32,
+    moving: bool,
+}
+
+impl Enemy {
+    /// Build the enemies of a map from their description
+    ///
+    /// # Arguments
+    /// * enemies - Enemy descriptions
+    /// * map - Map they stand in
+    ///
+    /// Return the enemies or the first error found
+    pub fn parse_enemies(enemies: &[EnemyData], map: &Map) -> Result<Vec<Enemy>, MapError> {
+        enemies.iter().enumerate().map(|(idx, enemy)| {
+            let pos = Vector3f::new(enemy.pos.x + 0.5, enemy.pos.y + 0.5, enemy.pos.z);
+
+            if !map.contains(&pos) {
+                return Err(MapError::new(format!("enemies[{}].pos", idx), "the enemy is outside of the map"));
+            }
+            let health = match enemy.health {
+                Some(0) => return Err(MapError::new(format!("enemies[{}].health", idx), "health must be positive")),
+                Some(health) => health,
+                None => HEALTH,
+            };
+            let mut patrol = Vec::with_capacity(enemy.patrol.len());
+
+            for (point_idx, point) in enemy.patrol.iter().enumerate() {
+                let point = Vector3f::new(point.x as f32 + 0.5, point.y as f32 + 0.5, pos.z);
+
+                if !map.contains(&point) {
+                    return Err(MapError::new(format!("enemies[{}].patrol[{}]", idx, point_idx), "the point is outside of the map"));
+                }
+                patrol.push(point);
+            }
+            let angle = enemy.angle.to_radians();
+
+            Ok(Enemy {
+                pos,
+                dir: Vector2f::new(angle.cos(), angle.sin()),
+                state: if patrol.is_empty() { EnemyState::Idle } else { EnemyState::Patrol },
+                health,
+                id: idx,
+                patrol,
+                waypoint: 0,
+                path: Vec::new(),
+                repath: 0.0,
+                last_seen: pos,
+                unseen: 0.0,
+                timer: 0.0,
+                cooldown: 0.0,
+                walk: 0.0,
+                moving: false,
+            })
+        }).collect()
+    }
+
+    /// Whether the enemy is dying or dead
+    pub fn is_down(&self) -> bool {
+        self.state == EnemyState::Death || self.state == EnemyState::Dead
+    }
+
+    /// Whether nothing stands between the eyes of the enemy and a position, straight or through the portals
+    ///
+    /// # Arguments
+    /// * map - Map holding the walls
+    /// * target - Position looked at, z being the floor level
+    pub fn can_see(&self, map: &Map, target: Vector3f) -> bool {
+        self.sight(map, target).is_some()
+    }
+
+    /// Line of sight to a position, `None` when it is hidden or too far
+    ///
+    /// Behind a portal, the position is looked at where it shows on the near side of the portal
+    fn sight(&self, map: &Map, target: Vector3f) -> Option<Sight> {
+        if let Some(sight) = self.look_at(map, target, false) {
+            return Some(sight);
+        }
+        map.linked_portals().find_map(|(source, dest)| {
+            let shown = Vector3f::new(source.link_x(dest, &target), source.link_y(dest, &target), target.z - dest.pos.z + source.pos.z);
+
+            self.look_at(map, shown, true)
+        })
+    }
+
+    /// Cast a ray from the eyes of the enemy to a position on the same floor
+    ///
+    /// # Arguments
+    /// * target - Position looked at
+    /// * portals - Whether the ray must go through a portal to reach it, or must not meet any
+    fn look_at(&self, map: &Map, target: Vector3f, portals: bool) -> Option<Sight> {
+        let to = Vector2f::new(target.x - self.pos.x, target.y - self.pos.y);
+        let dist = to.length();
+
+        if dist > SIGHT_DIST || target.z.floor() != self.pos.z.floor() {
+            return None;
+        }
+        if dist == 0.0 {
+            return (!portals).then_some(Sight { dist, dir: self.dir, portal: false });
+        }
+        let dir = Vector2f::new(to.x / dist, to.y / dist);
+        let mut ray = Ray::looking(self.pos, Vector3f::new(dir.x, dir.y, 0.0));
+        let mut crossed = false;
+
+        loop {
+            let hit = ray.cast(map);
+
+            if hit.value.is_none() || hit.dist >= dist {
+                return (crossed == portals).then_some(Sight { dist, dir, portal: crossed });
+            }
+            match map.portals_at(hit.pos, hit.dir) {
+                Some((Some(source), Some(dest))) if portals && ray.pass_through_portal(dest, source) => crossed = true,
+                _ => return None,
+            }
+        }
+    }
+
+    /// Whether the player is alive, in front of the enemy and visible
+    fn notices(&self, map: &Map, player: &Player) -> bool {
+        player.is_alive() && self.sight(map, player.pos).is_some_and(|sight| sight.dir.x * self.dir.x + sight.dir.y * self.dir.y >= 0.0)
+    }
+
+    /// Hurt the enemy
+    ///
+    /// # Arguments
+    /// * damage - Health lost
+    ///
+    /// Return the event of its death
+    pub fn hurt(&mut self, damage: u32) -> Option<Event> {
+        if self.is_down() {
+            return None;
+        }
+        self.health = self.health.saturating_sub(damage);
+        self.timer = 0.0;
+        if self.health == 0 {
+            self.state = EnemyState::Death;
+            return Some(Event::Killed { enemy: self.id });
+        }
+        self.state = EnemyState::Pain;
+        None
+    }
+
+    /// Move the enemy one step of its state machine
+    ///
+    /// # Arguments
+    /// * map - Map to walk in, doors on the way are opened
+    /// * player - Player to look for
+    /// * sprites - Sprites, the solid ones blocking the way
+    /// * delta - Elapsed time (Second)
+    ///
+    /// Return what happened, if anything
+    pub fn update(&mut self, map: &mut Map, player: &Player, sprites: &[Sprite], delta: f32) -> Option<Event> {
+        let mut event = None;
+
+        self.timer += delta;
+        self.cooldown -= delta;
+        self.repath -= delta;
+        self.moving = false;
+        match self.state {
+            EnemyState::Idle | EnemyState::Patrol => {
+                if self.notices(map, player) {
+                    self.state = EnemyState::Chase;
+                    self.last_seen = player.pos;
+                    self.cooldown = FIRE_TIME;
+                    event = Some(Event::Alerted { enemy: self.id });
+                } else if self.state == EnemyState::Patrol {
+                    self.patrol(map, sprites, delta);
+                }
+            }
+            EnemyState::Chase => self.chase(map, player, sprites, delta),
+            EnemyState::Attack => {
+                let sight = self.sight(map, player.pos).filter(|_| player.is_alive());
+
+                if let Some(sight) = sight {
+                    self.dir = sight.dir;
+                }
+                if let Some(sight) = sight.filter(|_| self.timer >= FIRE_TIME && self.timer - delta < FIRE_TIME) {
+                    let damage = SHOT_DAMAGE * (1.0 - 0.5 * (sight.dist / ATTACK_DIST).min(1.0));
+
+                    event = Some(Event::Fired { enemy: self.id, damage: damage.round() as u32 });
+                }
+                if self.timer >= ATTACK_TIME {
+                    self.state = EnemyState::Chase;
+                    self.cooldown = ATTACK_COOLDOWN;
+                }
+            }
+            EnemyState::Pain => {
+                if self.timer >= PAIN_TIME {
+                    self.state = EnemyState::Chase;
+                    self.last_seen = player.pos;
+                    self.unseen = 0.0;
+                }
+            }
+            EnemyState::Death => {
+                if self.timer >= DEATH_TIME {
+                    self.state = EnemyState::Dead;
+                }
+            }
+            EnemyState::Dead => {}
+        }
+        if self.moving {
+            self.walk += delta;
+        } else {
+            self.walk = 0.0;
+        }
+        event
+    }
+
+    fn patrol(&mut self, map: &mut Map, sprites: &[Sprite], delta: f32) {
+        let target = self.patrol[self.waypoint];
+
+        if self.travel(map, sprites, target, PATROL_SPEED * delta, 0.0) {
+            self.waypoint = (self.waypoint + 1) % self.patrol.len();
+            self.repath = 0.0;
+        }
+    }
+
+    fn chase(&mut self, map: &mut Map, player: &Player, sprites: &[Sprite], delta: f32) {
+        if let Some(sight) = self.sight(map, player.pos).filter(|_| player.is_alive()) {
+            self.last_seen = player.pos;
+            self.unseen = 0.0;
+            if self.cooldown <= 0.0 && sight.dist <= ATTACK_DIST {
+                self.dir = sight.dir;
+                self.state = EnemyState::Attack;
+                self.timer = 0.0;
+                return;
+            }
+            if sight.portal {
+                //Seen on the other side of a portal, the way there goes through it
+                self.travel(map, sprites, player.pos, CHASE_SPEED * delta, CLOSE_DIST);
+            } else {
+                self.path.clear();
+                self.walk_toward(map, sprites, Vector2f::new(player.pos.x, player.pos.y), CHASE_SPEED * delta, CLOSE_DIST);
+            }
+            return;
+        }
+        self.unseen += delta;
+        if self.unseen >= LOSE_TIME || !player.is_alive() {
+            //The player got away
+            self.state = if self.patrol.is_empty() { EnemyState::Idle } else { EnemyState::Patrol };
+            self.path.clear();
+            self.repath = 0.0;
+            return;
+        }
+        self.travel(map, sprites, self.last_seen, CHASE_SPEED * delta, 0.0);
+    }
+
+    /// Walk toward a position along a path, searched again every `REPATH_TIME`
+    ///
+    /// # Arguments
+    /// * map - Map to walk in
+    /// * sprites - Sprites, the solid ones blocking the way
+    /// * target - Position to reach, z being the height of the feet
+    /// * step - Distance walked
+    /// * stop - Distance from the target at which the enemy stops
+    ///
+    /// Return whether the target is reached, walking straight to it when no path leads there
+    fn travel(&mut self, map: &mut Map, sprites: &[Sprite], target: Vector3f, step: f32, stop: f32) -> bool {
+        if self.repath <= 0.0 {
+            self.repath = REPATH_TIME;
+            self.path = navigation::find_path(map, self.pos, target, &NavOptions::default()).unwrap_or_default();
+            //The last cell is crossed straight to the target
+            if self.path.last().is_some_and(|waypoint| !waypoint.portal) {
+                self.path.pop();
+            }
+        }
+        let waypoint = match self.path.first() {
+            Some(waypoint) => *waypoint,
+            None => return self.walk_toward(map, sprites, Vector2f::new(target.x, target.y), step, stop) == Walk::Arrived,
+        };
+
+        match self.walk_toward(map, sprites, Vector2f::new(waypoint.pos.x, waypoint.pos.y), step, 0.0) {
+            Walk::Arrived => {
+                self.path.remove(0);
+            }
+            Walk::Teleported if waypoint.portal => {
+                self.path.remove(0);
+            }
+            Walk::Teleported => {
+                //Pushed into a portal on the way, the path does not hold anymore
+                self.path.clear();
+                self.repath = 0.0;
+            }
+            Walk::Walking => {}
+        }
+        false
+    }
+
+    /// Turn toward a position
+    fn face(&mut self, target: Vector2f) {
+        let to = Vector2f::new(target.x - self.pos.x, target.y - self.pos.y);
+        let dist = to.length();
+
+        if dist > 0.0 {
+            self.dir = Vector2f::new(to.x / dist, to.y / dist);
+        }
+    }
+
+    /// Walk toward a position, sliding along the walls and the solid sprites, and crossing the portals
+    ///
+    /// # Arguments
+    /// * map - Map to walk in
+    /// * sprites - Sprites, the solid ones blocking the way
+    /// * target - Position to reach
+    /// * step - Distance walked
+    /// * stop - Distance from the target at which the enemy stops
+    ///
+    /// Return how the walk went
+    fn walk_toward(&mut self, map: &mut Map, sprites: &[Sprite], target: Vector2f, step: f32, stop: f32) -> Walk {
+        let dist = (target.x - self.pos.x).hypot(target.y - self.pos.y);
+
+        if dist <= stop + 0.05 {
+            return Walk::Arrived;
+        }
+        self.face(target);
+        let step = step.min(dist - stop);
+        let new_x = self.pos.x + self.dir.x * step;
+        let new_y = self.pos.y + self.dir.y * step;
+        let probe = Vector3f::new((new_x + RADIUS * self.dir.x.signum()).floor(), (new_y + RADIUS * self.dir.y.signum()).floor(), self.pos.z.floor());
+
+        if let Some((Some(source), Some(dest))) = map.portals_at(probe, Direction::None) {
+            let (pos, rotation) = navigation::through_portal(source, dest, self.pos);
+
+            self.pos = pos;
+            self.dir.rotate(rotation);
+            self.moving = true;
+            return Walk::Teleported;
+        }
+        let along_x = Vector3f::new(new_x, self.pos.y, self.pos.z);
+
+        if self.enter(map, Vector3f::new(new_x + RADIUS * self.dir.x.signum(), self.pos.y, self.pos.z)) && !Sprite::blocks(sprites, self.pos, along_x, RADIUS) {
+            self.pos.x = new_x;
+            self.moving = true;
+        }
+        let along_y = Vector3f::new(self.pos.x, new_y, self.pos.z);
+
+        if self.enter(map, Vector3f::new(self.pos.x, new_y + RADIUS * self.dir.y.signum(), self.pos.z)) && !Sprite::blocks(sprites, self.pos, along_y, RADIUS) {
+            self.pos.y = new_y;
+            self.moving = true;
+        }
+        if let Some(height) = navigation::stand_height(map, self.pos, &NavOptions::default()) {
+            self.pos.z = height;
+        }
+        Walk::Walking
+    }
+
+    /// Whether the enemy may stand at a position, opening the doors in its way, locked ones aside
+    fn enter(&self, map: &mut Map, pos: Vector3f) -> bool {
+        let door = match map.get(&pos) {
+            Cell::Thin(thin) if thin.value() >= DOOR_VALUE => Some(thin.slide() <= 0.0),
+            _ => None,
+        };
+
+        match door {
+            Some(open) => {
+                if !open {
+                    //Enemies hold no keys
+                    map.get_mut(&pos).trigger(&BTreeSet::new());
+                }
+                open
+            }
+            None => navigation::stand_height(map, Vector3f::new(pos.x, pos.y, self.pos.z), &NavOptions::default()).is_some(),
+        }
+    }
+
+    /// Row of the enemy sheet to draw, and whether it has a column per view
+    ///
+    /// # Arguments
+    /// * rows - Number of rows of the sheet, the states without a row showing the standing one
+    pub fn frame(&self, rows: u32) -> (u32, bool) {
+        let (row, directional) = match self.state {
+            EnemyState::Idle => (STAND_ROW, true),
+            EnemyState::Patrol | EnemyState::Chase if self.moving => {
+                (WALK_ROW + (self.walk / WALK_FRAME) as u32 % 4, true)
+            }
+            EnemyState::Patrol | EnemyState::Chase => (STAND_ROW, true),
+            EnemyState::Attack => (if self.timer < FIRE_TIME { AIM_ROW } else { FIRE_ROW }, false),
+            EnemyState::Pain => (PAIN_ROW, false),
+            EnemyState::Death => (DEATH_ROW + ((self.timer / DEATH_TIME * (DEATH_FRAMES - 1) as f32) as u32).min(DEATH_FRAMES - 2), false),
+            EnemyState::Dead => (DEATH_ROW + DEATH_FRAMES - 1, false),
+        };
+
+        if row < rows {
+            (row, directional)
+        } else {
+            (STAND_ROW, true)
+        }
+    }
+}
+
+/// Line of sight from an enemy
+#[derive(Clone, Copy)]
+struct Sight {
+    /// Distance to the position seen, along the line of sight
+    dist: f32,
+    /// Direction the enemy looks in to see it, normalized
+    dir: Vector2f,
+    /// Whether the line of sight goes through a portal
+    portal: bool,
+}
+
+/// Outcome of a step toward a position
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Walk {
+    Walking,
+    Arrived,
+    /// Walked into a portal and came out on the other side
+    Teleported,
+}
@@ -0,0 +1,39 @@
+use serde_derive::Serialize;
+
+use super::sprite::PickupKind;
+
+///
+/// Game event
+///
+/// Something which happened during an update, for the host to play sounds or draw its UI
+///
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// An enemy saw the player
+    Alerted { enemy: usize },
+    /// An enemy fired at the player
+    Fired { enemy: usize, damage: u32 },
+    /// An enemy was killed
+    Killed { enemy: usize },
+    /// The player fired a weapon, by its index
+    Shot { weapon: usize },
+    /// A shot of the player wounded an enemy without killing it
+    Hit { enemy: usize, damage: u32 },
+    /// The player lost health, the part taken by the armor left out
+    Hurt { damage: u32 },
+    /// The player died
+    Died,
+    /// The player went through a checkpoint, where it respawns from now on
+    Checkpoint,
+    /// The player came back to life
+    Respawned,
+    /// The player reached an exit, ending the level
+    Exited,
+    /// The player pushed a secret wall
+    Secret,
+    /// The player tried to open a door without its key
+    Locked { key: String },
+    /// The player collected an item, `item` being its ammunition type, weapon name or key ID
+    Picked { kind: PickupKind, amount: u32, item: Option<String> },
+}
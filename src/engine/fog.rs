@@ -0,0 +1,111 @@
+use super::schema::{FogData, FalloffData, MapError};
+
+use crate::graphics::RGBColor;
+
+/// Sharpness of the exponential falloff
+const EXPONENTIAL_DENSITY: f32 = 4.0;
+
+/// How the fog thickens between its start and its end
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Falloff {
+    Linear,
+    /// Thickens quickly past the start, then slowly up to the end
+    Exponential,
+}
+
+impl From<FalloffData> for Falloff {
+    fn from(falloff: FalloffData) -> Falloff {
+        match falloff {
+            FalloffData::Linear => Falloff::Linear,
+            FalloffData::Exponential => Falloff::Exponential,
+        }
+    }
+}
+
+///
+/// Distance fog
+///
+/// Blends what is seen toward the fog color between the start and the end
+/// distances, a black fog attenuates the light
+///
+#[derive(Clone, Copy)]
+pub struct Fog {
+    pub color: RGBColor,
+    pub start: f32,
+    pub end: f32,
+    pub falloff: Falloff,
+}
+
+impl Fog {
+    /// Build a fog from its description
+    ///
+    /// # Arguments
+    /// * data - Fog description
+    /// * path - JSON path of the description, used in errors
+    ///
+    /// Return the fog or the error
+    pub fn from_data(data: &FogData, path: &str) -> Result<Fog, MapError> {
+        if data.start.is_nan() || data.start < 0.0 {
+            return Err(MapError::new(format!("{}.start", path), format!("start must be positive, got {}", data.start)));
+        }
+        if data.end.is_nan() || data.end <= data.start {
+            return Err(MapError::new(format!("{}.end", path), format!("end must be past the start ({}), got {}", data.start, data.end)));
+        }
+        let [r, g, b] = data.color;
+
+        Ok(Fog { color: RGBColor::new(r, g, b), start: data.start, end: data.end, falloff: Falloff::from(data.falloff) })
+    }
+
+    /// Share of the fog in what is seen at a distance, from 0.0 to 1.0
+    pub fn density(&self, dist: f32) -> f32 {
+        let progress = ((dist - self.start) / (self.end - self.start)).clamp(0.0, 1.0);
+
+        match self.falloff {
+            Falloff::Linear => progress,
+            Falloff::Exponential => (1.0 - (-EXPONENTIAL_DENSITY * progress).exp()) / (1.0 - (-EXPONENTIAL_DENSITY).exp()),
+        }
+    }
+
+    /// Color seen through the fog
+    ///
+    /// # Arguments
+    /// * color - Color of what is seen
+    /// * dist - Distance of what is seen
+    ///
+    /// Return the fogged color, with the alpha of `color`
+    pub fn apply(&self, color: RGBColor, dist: f32) -> RGBColor {
+        if dist <= self.start {
+            return color;
+        }
+        color.mix(&self.color, self.density(dist))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fog(falloff: Falloff) -> Fog {
+        Fog { color: RGBColor::new(0, 0, 0), start: 4.0, end: 20.0, falloff }
+    }
+
+    #[test]
+    fn density_bounds() {
+        for falloff in [Falloff::Linear, Falloff::Exponential] {
+            let fog = fog(falloff);
+
+            //None before the start, all of it past the end
+            assert_eq!(fog.density(0.0), 0.0);
+            assert_eq!(fog.density(fog.start), 0.0);
+            assert_eq!(fog.density(fog.end), 1.0);
+            assert_eq!(fog.density(1000.0), 1.0);
+        }
+    }
+
+    #[test]
+    fn density_falloff() {
+        assert!((fog(Falloff::Linear).density(12.0) - 0.5).abs() < 1e-6);
+        //Thicker than the linear fog halfway
+        assert!(fog(Falloff::Exponential).density(12.0) > 0.8);
+    }
+}
@@ -0,0 +1,389 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::vectors::Vector2f;
+
+///
+/// Settings loading error
+///
+/// Carries the JSON path of the faulty field, e.g. `fire[1]`
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct SettingsError {
+    /// JSON path of the faulty field
+    pub path: String,
+    /// What is wrong with it
+    pub message: String,
+}
+
+impl SettingsError {
+    pub fn new(path: impl Into<String>, message: impl Into<String>) -> SettingsError {
+        SettingsError {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+/// What the player can do, whatever the key
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    StrafeLeft,
+    StrafeRight,
+    LookLeft,
+    LookRight,
+    LookUp,
+    LookDown,
+    Jump,
+    Interact,
+    Fire,
+    Weapon1,
+    Weapon2,
+    Weapon3,
+    Weapon4,
+    Weapon5,
+    Weapon6,
+    Weapon7,
+    Weapon8,
+    Weapon9,
+}
+
+/// Actions selecting the weapons, by index
+const WEAPONS: [Action; 9] = [
+    Action::Weapon1, Action::Weapon2, Action::Weapon3,
+    Action::Weapon4, Action::Weapon5, Action::Weapon6,
+    Action::Weapon7, Action::Weapon8, Action::Weapon9,
+];
+
+impl Action {
+    /// Index of the weapon selected by the action
+    pub fn weapon(self) -> Option<usize> {
+        WEAPONS.iter().position(|&action| action == self)
+    }
+}
+
+/// Key of the host, a string or a number in JSON, or a gamepad button such as `{"button": 0}`
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[serde(untagged)]
+pub enum Key {
+    /// `KeyboardEvent.code`, e.g. "KeyW", the same physical key whatever the layout
+    Code(String),
+    /// `KeyboardEvent.which` or any other numeric code
+    Which(u32),
+    /// Index of a button in `Gamepad.buttons`, in the standard mapping 0 is the bottom face button
+    Button { button: u32 },
+}
+
+impl From<&str> for Key {
+    fn from(code: &str) -> Key {
+        Key::Code(code.to_string())
+    }
+}
+
+impl From<u32> for Key {
+    fn from(which: u32) -> Key {
+        Key::Which(which)
+    }
+}
+
+///
+/// Keys bound to each action
+///
+/// Several keys may trigger an action, a key triggers a single one.
+/// Saved as JSON, e.g. `{"move_forward": ["KeyW", "ArrowUp", 87], "fire": ["ControlLeft"]}`
+///
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(transparent)]
+pub struct Bindings {
+    actions: BTreeMap<Action, Vec<Key>>,
+}
+
+impl Default for Bindings {
+    /// Codes of a QWERTY keyboard, the arrows, the `KeyboardEvent.which` numbers of the first versions,
+    /// and the directional pad, face buttons and right trigger of a standard gamepad
+    fn default() -> Bindings {
+        let mut bindings = Bindings { actions: BTreeMap::new() };
+        let keys: [(Action, &[Key]); 11] = [
+            (Action::MoveForward, &[Key::Which(90), Key::Which(87), "KeyW".into(), "ArrowUp".into(), Key::Button { button: 12 }]),
+            (Action::MoveBackward, &[Key::Which(83), "KeyS".into(), "ArrowDown".into(), Key::Button { button: 13 }]),
+            (Action::StrafeLeft, &[Key::Which(81), "KeyQ".into(), "Comma".into(), Key::Button { button: 14 }]),
+            (Action::StrafeRight, &["KeyE".into(), "Period".into(), Key::Button { button: 15 }]),
+            (Action::LookLeft, &[Key::Which(65), "KeyA".into(), "ArrowLeft".into()]),
+            (Action::LookRight, &[Key::Which(68), "KeyD".into(), "ArrowRight".into()]),
+            (Action::LookUp, &[Key::Which(33), "PageUp".into()]),
+            (Action::LookDown, &[Key::Which(34), "PageDown".into()]),
+            (Action::Jump, &[Key::Which(32), "Space".into(), Key::Button { button: 0 }]),
+            (Action::Interact, &[Key::Which(70), "KeyF".into(), Key::Button { button: 2 }]),
+            (Action::Fire, &[Key::Which(17), "ControlLeft".into(), "ControlRight".into(), Key::Button { button: 7 }]),
+        ];
+
+        for (action, keys) in keys.iter() {
+            for key in keys.iter() {
+                bindings.bind(*action, key.clone());
+            }
+        }
+        for (idx, action) in WEAPONS.iter().enumerate() {
+            bindings.bind(*action, Key::Which(49 + idx as u32));
+            bindings.bind(*action, Key::Code(format!("Digit{}", idx + 1)));
+        }
+        bindings
+    }
+}
+
+impl Bindings {
+    /// Bindings without any key
+    pub fn empty() -> Bindings {
+        Bindings { actions: BTreeMap::new() }
+    }
+
+    /// Load bindings saved by [`to_json`](Bindings::to_json), reporting the path of the first invalid field
+    ///
+    /// # Arguments
+    /// * json - Keys by action
+    ///
+    /// Return the bindings or the error, e.g. a key bound to two actions
+    pub fn from_json(json: &serde_json::Value) -> Result<Bindings, SettingsError> {
+        let bindings: Bindings = serde_path_to_error::deserialize(json).map_err(|err| {
+            SettingsError::new(err.path().to_string(), err.inner().to_string())
+        })?;
+        let mut bound = BTreeMap::new();
+
+        for (action, keys) in &bindings.actions {
+            for (idx, key) in keys.iter().enumerate() {
+                if let Some(other) = bound.insert(key, *action) {
+                    return Err(SettingsError::new(format!("{}[{}]", name(*action), idx), format!("the key is already bound to {}", name(other))));
+                }
+            }
+        }
+        Ok(bindings)
+    }
+
+    /// Save the bindings, to be loaded by [`from_json`](Bindings::from_json)
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap()
+    }
+
+    /// Bind a key to an action, taking it from the action it triggered
+    pub fn bind(&mut self, action: Action, key: Key) {
+        self.unbind(&key);
+        self.actions.entry(action).or_default().push(key);
+    }
+
+    /// Remove a key from the action it triggers
+    pub fn unbind(&mut self, key: &Key) {
+        for keys in self.actions.values_mut() {
+            keys.retain(|bound| bound != key);
+        }
+        self.actions.retain(|_, keys| !keys.is_empty());
+    }
+
+    /// Keys triggering an action
+    pub fn keys(&self, action: Action) -> &[Key] {
+        self.actions.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Action triggered by a key, if it is bound
+    pub fn action(&self, key: &Key) -> Option<Action> {
+        self.actions.iter().find(|(_, keys)| keys.contains(key)).map(|(action, _)| *action)
+    }
+}
+
+/// Name of an action in JSON, e.g. "move_forward"
+fn name(action: Action) -> String {
+    serde_json::to_value(action).ok().and_then(|name| name.as_str().map(str::to_string)).unwrap_or_default()
+}
+
+///
+/// Mouse look settings
+///
+/// Saved as JSON, e.g. `{"sensitivity": 0.0025, "smoothing": 0.05, "invert_x": false, "invert_y": true}`
+///
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct MouseSettings {
+    /// Rotation per pixel moved (Radian)
+    pub sensitivity: f32,
+    /// Time taken to catch up with about two thirds of a move, 0 turns at once (Second)
+    pub smoothing: f32,
+    /// Whether moving right turns left
+    pub invert_x: bool,
+    /// Whether moving down looks up
+    pub invert_y: bool,
+}
+
+impl Default for MouseSettings {
+    fn default() -> MouseSettings {
+        MouseSettings { sensitivity: 0.0025, smoothing: 0.0, invert_x: false, invert_y: false }
+    }
+}
+
+impl MouseSettings {
+    /// Load settings saved as JSON, reporting the path of the first invalid field
+    ///
+    /// # Arguments
+    /// * json - Settings, the missing ones keeping their default
+    ///
+    /// Return the settings or the error
+    pub fn from_json(json: &serde_json::Value) -> Result<MouseSettings, SettingsError> {
+        let settings: MouseSettings = serde_path_to_error::deserialize(json).map_err(|err| {
+            SettingsError::new(err.path().to_string(), err.inner().to_string())
+        })?;
+
+        if !settings.sensitivity.is_finite() || settings.sensitivity < 0.0 {
+            return Err(SettingsError::new("sensitivity", "the sensitivity must not be negative"));
+        }
+        if !settings.smoothing.is_finite() || settings.smoothing < 0.0 {
+            return Err(SettingsError::new("smoothing", "the smoothing must not be negative"));
+        }
+        Ok(settings)
+    }
+
+    /// Rotations of a mouse move, x to the right and y downward (Radian)
+    ///
+    /// # Arguments
+    /// * dx - Horizontal move, e.g. `MouseEvent.movementX` (Pixel)
+    /// * dy - Vertical move, e.g. `MouseEvent.movementY` (Pixel)
+    pub fn rotation(&self, dx: f32, dy: f32) -> Vector2f {
+        let sign = |inverted: bool| if inverted { -1.0 } else { 1.0 };
+
+        Vector2f::new(dx * self.sensitivity * sign(self.invert_x), dy * self.sensitivity * sign(self.invert_y))
+    }
+
+    /// Part of the rotation left to apply which is applied during some time
+    ///
+    /// # Arguments
+    /// * pending - Rotation left to apply (Radian)
+    /// * delta - Elapsed time (Second)
+    pub fn smoothed(&self, pending: Vector2f, delta: f32) -> Vector2f {
+        if self.smoothing <= 0.0 {
+            return pending;
+        }
+        let share = 1.0 - (-delta / self.smoothing).exp();
+
+        Vector2f::new(pending.x * share, pending.y * share)
+    }
+}
+
+/// Analog axis of a gamepad, from -1 to 1
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Axis {
+    /// Walking stick, right positive, `Gamepad.axes[0]` in the standard mapping
+    MoveX,
+    /// Walking stick, down positive, `Gamepad.axes[1]`
+    MoveY,
+    /// Looking stick, right positive, `Gamepad.axes[2]`
+    LookX,
+    /// Looking stick, down positive, `Gamepad.axes[3]`
+    LookY,
+}
+
+///
+/// Gamepad settings
+///
+/// Saved as JSON, e.g. `{"dead_zone": 0.15, "curve": 2, "invert_y": false}`
+///
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct GamepadSettings {
+    /// Deflection of a stick under which it counts as centered, from 0 to 1
+    pub dead_zone: f32,
+    /// Exponent of the response past the dead zone, 1 being linear and higher values finer near the center
+    pub curve: f32,
+    /// Whether pushing the looking stick down looks up
+    pub invert_y: bool,
+}
+
+impl Default for GamepadSettings {
+    fn default() -> GamepadSettings {
+        GamepadSettings { dead_zone: 0.15, curve: 2.0, invert_y: false }
+    }
+}
+
+impl GamepadSettings {
+    /// Load settings saved as JSON, reporting the path of the first invalid field
+    ///
+    /// # Arguments
+    /// * json - Settings, the missing ones keeping their default
+    ///
+    /// Return the settings or the error
+    pub fn from_json(json: &serde_json::Value) -> Result<GamepadSettings, SettingsError> {
+        let settings: GamepadSettings = serde_path_to_error::deserialize(json).map_err(|err| {
+            SettingsError::new(err.path().to_string(), err.inner().to_string())
+        })?;
+
+        if !(0.0..1.0).contains(&settings.dead_zone) {
+            return Err(SettingsError::new("dead_zone", "the dead zone must lie between 0 and 1"));
+        }
+        if !settings.curve.is_finite() || settings.curve <= 0.0 {
+            return Err(SettingsError::new("curve", "the curve must be positive"));
+        }
+        Ok(settings)
+    }
+
+    /// Deflection of a stick once its dead zone and response curve are applied
+    ///
+    /// The dead zone is radial, so that diagonals are not snapped to the axes,
+    /// and the deflection past it is rescaled to start from 0
+    ///
+    /// # Arguments
+    /// * x - Horizontal axis, from -1 to 1
+    /// * y - Vertical axis, from -1 to 1
+    ///
+    /// Return the deflection, of length up to 1
+    pub fn stick(&self, x: f32, y: f32) -> Vector2f {
+        let length = x.hypot(y);
+
+        if length <= self.dead_zone {
+            return Vector2f::default();
+        }
+        let scale = ((length.min(1.0) - self.dead_zone) / (1.0 - self.dead_zone)).powf(self.curve) / length;
+
+        Vector2f::new(x * scale, y * scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gamepad(dead_zone: f32, curve: f32) -> GamepadSettings {
+        GamepadSettings { dead_zone, curve, invert_y: false }
+    }
+
+    #[test]
+    fn stick_dead_zone() {
+        let settings = gamepad(0.2, 1.0);
+
+        //Centered up to the edge of the dead zone, moving right past it
+        assert_eq!((settings.stick(0.2, 0.0).x, settings.stick(0.0, -0.2).y), (0.0, 0.0));
+        let past = settings.stick(0.21, 0.0);
+
+        assert!(past.x > 0.0 && past.x < 0.02);
+        assert_eq!(settings.stick(1.0, 0.0).x, 1.0);
+    }
+
+    #[test]
+    fn stick_curve() {
+        let (linear, quadratic) = (gamepad(0.0, 1.0), gamepad(0.0, 2.0));
+
+        assert!((linear.stick(0.5, 0.0).x - 0.5).abs() < 1e-6);
+        assert!((quadratic.stick(0.5, 0.0).x - 0.25).abs() < 1e-6);
+        //Both reach the full deflection, a diagonal pushed to the corner no further
+        assert_eq!((linear.stick(0.0, 1.0).y, quadratic.stick(0.0, 1.0).y), (1.0, 1.0));
+        assert!((quadratic.stick(1.0, 1.0).length() - 1.0).abs() < 1e-6);
+    }
+}
@@ -1,98 +1,350 @@
+use std::collections::BTreeSet;
+
 use super::vectors::Vector3f;
 use super::direction::Direction;
 use super::cell::{Cell, Interaction};
+use super::event::Event;
+use super::pushwall::Pushwall;
 use super::portal::Portal;
+use super::surface::Surface;
+use super::fog::Fog;
+use super::schema::{MapData, MapError, CellData, PortalPairData, SurfaceData};
+use crate::graphics::TextureAtlas;
 
-type Cells = Vec<Vec<Vec<Cell>>>;
 type Portals = Vec<(Option<Portal>, Option<Portal>)>;
 
+/// Distance from a cell within which someone stands in the way of a pushwall entering it
+const OCCUPANT_MARGIN: f32 = 0.3;
+/// Most cells a map may hold, all floors included
+pub const MAX_MAP_CELLS: usize = 1 << 22;
+
 pub struct Map {
-    cells: Cells,
+    width: usize,
+    height: usize,
+    floors: usize,
+    cells: Vec<Cell>,
+    surfaces: Vec<Surface>,
+    /// Health lost per second in each cell
+    hazards: Vec<f32>,
+    checkpoints: Vec<bool>,
+    /// Whether each cell ends the level
+    exits: Vec<bool>,
+    /// Fog of each floor
+    fogs: Vec<Option<Fog>>,
     portals: Portals,
+    pushwalls: Vec<Pushwall>,
     empty_ref: Cell,
 }
 
 impl Map {
-    fn parse_cells_from_json(json: &serde_json::Value) -> Cells {
-        let mut cells = Cells::new();
+    fn parse_size(map: &MapData) -> Result<(usize, usize, usize), MapError> {
+        let cells = map.cells.iter().flatten();
+        let width = match map.width {
+            Some(width) => width,
+            None => cells.clone().map(|cell| cell.pos.x + 1).max().unwrap_or(0),
+        };
+        let height = match map.height {
+            Some(height) => height,
+            None => cells.map(|cell| cell.pos.y + 1).max().unwrap_or(0),
+        };
+        let floors = match map.floors {
+            Some(floors) if floors < map.cells.len() => {
+                return Err(MapError::new(format!("cells[{}]", floors), format!("only {} floors are declared", floors)));
+            }
+            Some(floors) => floors,
+            None => map.cells.len(),
+        };
+        //Sizes are checked one factor after the other, so that the error names the field going past the limit
+        let too_large = |path: &str| MapError::new(path, format!("the map holds more than {} cells", MAX_MAP_CELLS));
+
+        if width > MAX_MAP_CELLS {
+            return Err(too_large("width"));
+        }
+        let area = width.checked_mul(height).filter(|&area| area <= MAX_MAP_CELLS).ok_or_else(|| too_large("height"))?;
+
+        area.checked_mul(floors).filter(|&size| size <= MAX_MAP_CELLS).ok_or_else(|| too_large("floors"))?;
+        Ok((width, height, floors))
+    }
+
+    fn parse_cells(&mut self, floors: &[Vec<CellData>], atlas: &TextureAtlas) -> Result<(), MapError> {
+        for (z, list) in floors.iter().enumerate() {
+            for (idx, cell) in list.iter().enumerate() {
+                let path = format!("cells[{}][{}]", z, idx);
 
-        for list in json.as_array().unwrap() {
-            let mut floor: Vec<Vec<Cell>> = (0..100).map(|_| {
-                (0..100).map(|_| {
-                    Cell::default()
-                }).collect()
-            }).collect();
+                if cell.pos.x >= self.width {
+                    return Err(MapError::new(format!("{}.pos.x", path), format!("x is {} but the map is {} cells wide", cell.pos.x, self.width)));
+                }
+                if cell.pos.y >= self.height {
+                    return Err(MapError::new(format!("{}.pos.y", path), format!("y is {} but the map is {} cells high", cell.pos.y, self.height)));
+                }
+                let offset = self.offset(cell.pos.x, cell.pos.y, z);
 
-            for cell in list.as_array().unwrap() {
-                let x = cell["pos"]["x"].as_u64().unwrap() as usize;
-                let y = cell["pos"]["y"].as_u64().unwrap() as usize;
+                self.cells[offset] = Cell::from_data(cell, &path, atlas)?;
+                self.surfaces[offset] = self.surfaces[offset].overridden(cell.floor, cell.ceiling, &path, atlas)?;
+                if cell.hazard.is_nan() || cell.hazard < 0.0 {
+                    return Err(MapError::new(format!("{}.hazard", path), "the damage must not be negative"));
+                }
+                self.hazards[offset] = cell.hazard;
+                self.checkpoints[offset] = cell.checkpoint;
+                self.exits[offset] = cell.exit;
+                if cell.pushable {
+                    if cell.push_distance == Some(0) {
+                        return Err(MapError::new(format!("{}.push_distance", path), "the distance must be positive"));
+                    }
+                    let pos = Vector3f::new(cell.pos.x as f32, cell.pos.y as f32, z as f32);
 
-                floor[x][y] = Cell::from_json(cell);
+                    self.pushwalls.push(Pushwall::new(pos, self.cells[offset].value(), cell.push_distance));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_surfaces(&mut self, surfaces: &[SurfaceData], atlas: &TextureAtlas) -> Result<(), MapError> {
+        if surfaces.len() > self.floors {
+            return Err(MapError::new(format!("surfaces[{}]", self.floors), format!("only {} floors are declared", self.floors)));
+        }
+        for (z, data) in surfaces.iter().enumerate() {
+            let surface = Surface::from_data(data, &format!("surfaces[{}]", z), atlas)?;
+            let start = self.offset(0, 0, z);
+
+            self.surfaces[start..start + self.width * self.height].fill(surface);
+        }
+        Ok(())
+    }
+
+    fn parse_fogs(&mut self, map: &MapData) -> Result<(), MapError> {
+        let fog = match &map.fog {
+            Some(fog) => Some(Fog::from_data(fog, "fog")?),
+            None => None,
+        };
+
+        if map.floor_fogs.len() > self.floors {
+            return Err(MapError::new(format!("floor_fogs[{}]", self.floors), format!("only {} floors are declared", self.floors)));
+        }
+        self.fogs = vec![fog; self.floors];
+        for (z, data) in map.floor_fogs.iter().enumerate() {
+            if let Some(data) = data {
+                self.fogs[z] = Some(Fog::from_data(data, &format!("floor_fogs[{}]", z))?);
             }
-            cells.push(floor);
         }
-        cells
+        Ok(())
     }
 
-    fn parse_portals_from_json(json: &serde_json::Value) -> Portals {
+    fn parse_portals(&self, pairs: &[PortalPairData]) -> Result<Portals, MapError> {
         let mut portals = Portals::new();
 
-        if let Some(list) = json.as_array() {
-            for portal in list {
-                portals.push((Portal::from_json(&portal["first"]), Portal::from_json(&portal["second"])));
+        for (idx, pair) in pairs.iter().enumerate() {
+            for (name, portal) in &[("first", &pair.first), ("second", &pair.second)] {
+                if let Some(portal) = portal {
+                    if self.index(&Vector3f::from(portal.pos)).is_none() {
+                        return Err(MapError::new(format!("portals[{}].{}.pos", idx, name), "the portal is outside of the map"));
+                    }
+                }
             }
+            portals.push((pair.first.as_ref().map(Portal::from_data), pair.second.as_ref().map(Portal::from_data)));
         }
-        portals
+        Ok(portals)
     }
 
-    pub fn new(map: &serde_json::Value) -> Map {
-        Map {
-            cells: Map::parse_cells_from_json(&map["cells"]),
-            portals: Map::parse_portals_from_json(&map["portals"]),
+    pub fn new(data: &MapData, atlas: &TextureAtlas) -> Result<Map, MapError> {
+        let (width, height, floors) = Map::parse_size(data)?;
+        let mut map = Map {
+            width,
+            height,
+            floors,
+            cells: (0..width * height * floors).map(|_| Cell::default()).collect(),
+            surfaces: vec![Surface::default(); width * height * floors],
+            hazards: vec![0.0; width * height * floors],
+            checkpoints: vec![false; width * height * floors],
+            exits: vec![false; width * height * floors],
+            fogs: Vec::new(),
+            portals: Portals::new(),
+            pushwalls: Vec::new(),
             empty_ref: Cell::Empty,
-        }
+        };
+
+        map.parse_surfaces(&data.surfaces, atlas)?;
+        map.parse_fogs(data)?;
+        map.parse_cells(&data.cells, atlas)?;
+        map.portals = map.parse_portals(&data.portals)?;
+        Ok(map)
     }
 
+    /// Number of cells along x
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of cells along y
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Number of floors
     pub fn depth(&self) -> usize {
-        self.cells.len()
+        self.floors
+    }
+
+    fn offset(&self, x: usize, y: usize, z: usize) -> usize {
+        (z * self.width + x) * self.height + y
+    }
+
+    fn index(&self, position: &Vector3f) -> Option<usize> {
+        if position.x < 0.0 || position.y < 0.0 || position.z < 0.0 {
+            return None;
+        }
+        let (x, y, z) = (position.x as usize, position.y as usize, position.z as usize);
+
+        if x >= self.width || y >= self.height || z >= self.floors {
+            return None;
+        }
+        Some(self.offset(x, y, z))
     }
 
-    pub fn update(&mut self, delta: f32) {
-        for floor in &mut self.cells {
-            for row in floor {
-                for cell in row {
-                    cell.update(delta);
+    /// Whether a position is inside of the map
+    pub fn contains(&self, position: &Vector3f) -> bool {
+        self.index(position).is_some()
+    }
+
+    /// Move the doors and the pushwalls
+    ///
+    /// # Arguments
+    /// * delta - Elapsed time (Second)
+    /// * occupants - Positions of the player, the enemies and the solid sprites, no pushwall slides over them
+    pub fn update(&mut self, delta: f32, occupants: &[Vector3f]) {
+        for cell in &mut self.cells {
+            cell.update(delta);
+        }
+        for idx in 0..self.pushwalls.len() {
+            let wall = &self.pushwalls[idx];
+
+            if wall.sliding().is_none() {
+                continue;
+            }
+            match self.get(&wall.pos) {
+                //The block left its cell for the next one
+                Cell::Empty => {
+                    if !self.pushwalls[idx].advance() {
+                        self.pushwalls[idx].stop();
+                        continue;
+                    }
                 }
+                //Held back by someone in the way
+                Cell::Wall { .. } => {}
+                _ => continue,
             }
+            if self.slide(idx, occupants) == Slide::Blocked {
+                self.pushwalls[idx].stop();
+            }
+        }
+    }
+
+    /// Move a sliding pushwall out of its cell, if the next one is free
+    fn slide(&mut self, idx: usize, occupants: &[Vector3f]) -> Slide {
+        let wall = &self.pushwalls[idx];
+        let (pos, value) = (wall.pos, wall.value);
+        let (next, dir) = match (wall.next(), wall.sliding()) {
+            (Some(next), Some(dir)) => (next, dir),
+            _ => return Slide::Blocked,
+        };
+
+        if !self.contains(&next) || !matches!(self.get(&next), Cell::Empty) || self.portals_at(next, Direction::None).is_some() {
+            return Slide::Blocked;
+        }
+        let in_the_way = |occupant: &Vector3f| {
+            occupant.z.floor() == next.z &&
+                occupant.x > next.x - OCCUPANT_MARGIN && occupant.x < next.x + 1.0 + OCCUPANT_MARGIN &&
+                occupant.y > next.y - OCCUPANT_MARGIN && occupant.y < next.y + 1.0 + OCCUPANT_MARGIN
+        };
+
+        if occupants.iter().any(in_the_way) {
+            return Slide::Held;
+        }
+        *self.get_mut(&pos) = Cell::pushed(value, dir);
+        *self.get_mut(&next) = Cell::Wall { value, height: 1.0 };
+        Slide::Moved
+    }
+
+    /// Open or push a cell
+    ///
+    /// # Arguments
+    /// * pos - Cell triggered
+    /// * dir - Direction of the ray hitting it, the one pushwalls slide toward
+    /// * keys - Keys held by whoever triggers the cell, by ID
+    /// * occupants - Positions a pushwall waits for to be left before sliding over them
+    ///
+    /// Return the event of a refusal, or of a secret found
+    pub fn trigger(&mut self, pos: &Vector3f, dir: Direction, keys: &BTreeSet<String>, occupants: &[Vector3f]) -> Option<Event> {
+        let idx = match self.pushwalls.iter().position(|wall| wall.pos == *pos && !wall.is_found()) {
+            Some(idx) => idx,
+            None => return self.get_mut(pos).trigger(keys),
+        };
+        let wall = self.pushwalls[idx].clone();
+
+        if self.pushwalls[idx].push(dir) && self.slide(idx, occupants) != Slide::Blocked {
+            return Some(Event::Secret);
         }
+        //Blocked from the start, the wall keeps its secret
+        self.pushwalls[idx] = wall;
+        None
+    }
+
+    /// Number of secret pushwalls found, and in the map
+    pub fn secrets(&self) -> (usize, usize) {
+        (self.pushwalls.iter().filter(|wall| wall.is_found()).count(), self.pushwalls.len())
     }
 
     pub fn get(&self, position: &Vector3f) -> &Cell {
-        if position.z < 0.0 || position.z as usize > self.depth() {
-            return &self.empty_ref;
+        match self.index(position) {
+            Some(offset) => &self.cells[offset],
+            None => &self.empty_ref,
         }
-        if let Some(floor) = self.cells.get(position.z as usize) {
-            if let Some(line) = floor.get(position.x as usize) {
-                if let Some(cell) = line.get(position.y as usize) {
-                    return cell;
-                }
-            }
+    }
+
+    /// Floor and ceiling textures of a cell, untextured outside of the map
+    pub fn surface(&self, position: &Vector3f) -> Surface {
+        match self.index(position) {
+            Some(offset) => self.surfaces[offset],
+            None => Surface::default(),
         }
-        &self.empty_ref
     }
 
-    pub fn get_mut(&mut self, position: &Vector3f) -> &mut Cell {
-        if position.z < 0.0 || position.z as usize > self.cells.len() - 1 {
-            return &mut self.empty_ref;
+    /// Health lost per second in a cell, none outside of the map
+    pub fn hazard(&self, position: &Vector3f) -> f32 {
+        self.index(position).map_or(0.0, |offset| self.hazards[offset])
+    }
+
+    /// Whether a cell is a checkpoint
+    pub fn is_checkpoint(&self, position: &Vector3f) -> bool {
+        self.index(position).is_some_and(|offset| self.checkpoints[offset])
+    }
+
+    /// Whether a cell ends the level, a switch when it is a wall
+    pub fn is_exit(&self, position: &Vector3f) -> bool {
+        self.index(position).is_some_and(|offset| self.exits[offset])
+    }
+
+    /// Fog of the floor at a height, the closest floor outside of the map
+    pub fn fog(&self, z: f32) -> Option<Fog> {
+        if self.fogs.is_empty() {
+            return None;
         }
-        if let Some(floor) = self.cells.get_mut(position.z as usize) {
-            if let Some(line) = floor.get_mut(position.x as usize) {
-                if let Some(cell) = line.get_mut(position.y as usize) {
-                    return cell;
-                }
-            }
+        self.fogs[(z.max(0.0) as usize).min(self.fogs.len() - 1)]
+    }
+
+    pub fn get_mut(&mut self, position: &Vector3f) -> &mut Cell {
+        match self.index(position) {
+            Some(offset) => &mut self.cells[offset],
+            None => &mut self.empty_ref,
         }
-        &mut self.empty_ref
+    }
+
+    /// Portals leading to another one, with it, each pair being given both ways
+    pub fn linked_portals(&self) -> impl Iterator<Item = (&Portal, &Portal)> {
+        self.portals.iter()
+            .filter_map(|(first, second)| Some((first.as_ref()?, second.as_ref()?)))
+            .flat_map(|(first, second)| [(first, second), (second, first)])
     }
 
     pub fn portals_at(&self, position: Vector3f, dir: Direction) -> Option<(Option<&Portal>, Option<&Portal>)> {
@@ -114,3 +366,50 @@ impl Map {
         None
     }
 }
+
+/// Outcome of a pushwall trying to enter the next cell
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Slide {
+    Moved,
+    /// Someone stands in the next cell, the block waits
+    Held,
+    /// The next cell is not free, the block stops
+    Blocked,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Corridor with a pushwall pushed east from its second cell, and a wall in the third one if asked
+    fn corridor(wall: bool) -> Map {
+        let mut cells = vec![serde_json::json!({"pos": {"x": 1, "y": 0}, "value": 1, "height": 1.0, "pushable": true})];
+
+        if wall {
+            cells.push(serde_json::json!({"pos": {"x": 2, "y": 0}, "value": 1, "height": 1.0}));
+        }
+        let data = MapData::from_json(&serde_json::json!({"width": 4, "height": 1, "floors": 1, "cells": [cells]})).unwrap();
+        let mut atlas = TextureAtlas::new();
+
+        atlas.add_rgba("wall", 1, 1, &[255, 255, 255, 255]).unwrap();
+        atlas.bind_wall(1, "wall", None).unwrap();
+        let mut map = Map::new(&data, &atlas).unwrap();
+
+        assert!(map.pushwalls[0].push(Direction::East));
+        map
+    }
+
+    #[test]
+    fn slide_blocked() {
+        assert_eq!(corridor(true).slide(0, &[]), Slide::Blocked);
+    }
+
+    #[test]
+    fn slide_held() {
+        let mut map = corridor(false);
+
+        //Standing near the edge of the next cell is enough to hold the block
+        assert_eq!(map.slide(0, &[Vector3f::new(3.2, 0.5, 0.0)]), Slide::Held);
+        assert_eq!(map.slide(0, &[Vector3f::new(2.5, 0.5, 1.0)]), Slide::Moved);
+    }
+}
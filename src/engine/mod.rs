@@ -1,15 +1,32 @@
-pub use cell::Cell;
-pub use player::Player;
+pub use cell::{Cell, Thin, DOOR_VALUE};
+pub use player::{Player, Status, MAX_HEALTH, MAX_ARMOR, MAX_PITCH};
 pub use direction::Direction;
-pub use map::Map;
+pub use map::{Map, MAX_MAP_CELLS};
 pub use portal::Portal;
+pub use surface::Surface;
+pub use fog::{Fog, Falloff};
+pub use enemy::{Enemy, EnemyState};
+pub use event::Event;
+pub use weapon::{Weapon, Arsenal, Shot, Target};
+pub use stats::{Stats, Tally};
+pub use input::{Action, Key, Bindings, MouseSettings, Axis, GamepadSettings, SettingsError};
 
 mod map;
 mod portal;
+mod surface;
+mod fog;
+mod enemy;
+mod event;
+mod weapon;
+mod pushwall;
+mod stats;
+mod input;
 mod cell;
 mod player;
 mod direction;
 
 pub mod rayobject;
+pub mod schema;
 pub mod sprite;
+pub mod navigation;
 pub mod vectors;
\ No newline at end of file
@@ -0,0 +1,284 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use super::map::Map;
+use super::portal::Portal;
+use super::cell::{Cell, DOOR_VALUE};
+use super::direction::Direction;
+use super::vectors::{Vector2f, Vector3f};
+
+/// Tolerance on heights, for positions lying exactly on a floor
+const EPSILON: f32 = 1e-3;
+const NEIGHBOURS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+///
+/// What a walker can go through
+///
+#[derive(Clone, Copy, Debug)]
+pub struct NavOptions {
+    /// Highest step climbed without jumping, higher walls are obstacles
+    pub max_step: f32,
+    /// Highest drop jumped down
+    pub max_drop: f32,
+    /// Extra cost of going through a closed door, in cells
+    pub door_cost: f32,
+}
+
+impl Default for NavOptions {
+    fn default() -> NavOptions {
+        NavOptions {
+            max_step: 0.5,
+            max_drop: 1.0,
+            door_cost: 3.0,
+        }
+    }
+}
+
+///
+/// Step of a path
+///
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Waypoint {
+    /// Center of the cell to reach, z being the height stood at
+    pub pos: Vector3f,
+    /// Whether `pos` is a portal, walking into it leads to the next waypoint on its other side
+    pub portal: bool,
+}
+
+/// Height a walker ends at when entering a column, `None` when it is blocked
+///
+/// # Arguments
+/// * map - Map walked in
+/// * pos - Position in the column, z being the height of the feet before entering it
+/// * options - What the walker can go through
+pub fn stand_height(map: &Map, pos: Vector3f, options: &NavOptions) -> Option<f32> {
+    let level = (pos.z + EPSILON).floor();
+    let feet = Vector3f::new(pos.x, pos.y, level);
+
+    if !map.contains(&feet) {
+        return None;
+    }
+    let height = match map.get(&feet) {
+        Cell::Wall { height, .. } if *height >= 1.0 => {
+            //Climbing on top of a block, the cell above must be free
+            let above = Vector3f::new(pos.x, pos.y, level + 1.0);
+
+            match map.get(&above) {
+                Cell::Empty if map.contains(&above) => level + 1.0,
+                _ => return None,
+            }
+        }
+        Cell::Wall { height, .. } => level + height,
+        //Closed locked doors are walls, NPCs holding no keys
+        Cell::Thin(thin) if thin.value() >= DOOR_VALUE && (thin.lock().is_none() || thin.slide() <= 0.0) => level,
+        Cell::Thin(_) => return None,
+        Cell::Empty => {
+            //Falling down to the first thing below
+            let mut below = level - 1.0;
+
+            loop {
+                if below < 0.0 {
+                    break 0.0;
+                }
+                match map.get(&Vector3f::new(pos.x, pos.y, below)) {
+                    Cell::Empty => below -= 1.0,
+                    Cell::Wall { height, .. } => break below + height,
+                    Cell::Thin(_) => break below + 1.0,
+                }
+            }
+        }
+    };
+
+    if height - pos.z > options.max_step + EPSILON || pos.z - height > options.max_drop + EPSILON {
+        None
+    } else {
+        Some(height)
+    }
+}
+
+/// Where a walker crossing a portal comes out
+///
+/// # Arguments
+/// * source - Portal walked into
+/// * dest - Portal on the other side
+/// * pos - Position of the walker, in a cell next to the one of `source`
+///
+/// Return the new position, on the floor of `dest`, and the rotation to apply to the walker (Radian)
+pub fn through_portal(source: &Portal, dest: &Portal, pos: Vector3f) -> (Vector3f, f32) {
+    //Way in, and distance left to the face of the portal
+    let (mut normal, dist) = if pos.x < source.pos.x {
+        (Vector2f::new(1.0, 0.0), source.pos.x - pos.x)
+    } else if pos.x > source.pos.x + 1.0 {
+        (Vector2f::new(-1.0, 0.0), pos.x - source.pos.x - 1.0)
+    } else if pos.y < source.pos.y {
+        (Vector2f::new(0.0, 1.0), source.pos.y - pos.y)
+    } else {
+        (Vector2f::new(0.0, -1.0), pos.y - source.pos.y - 1.0)
+    };
+    let rotation = dest.link_dir(source);
+    let mut out = Vector3f::new(dest.link_x(source, &pos), dest.link_y(source, &pos), dest.pos.z);
+
+    //Facing portals put the walker as deep behind the face of `dest` as it was in front of `source`
+    if out.x.floor() == dest.pos.x && out.y.floor() == dest.pos.y {
+        normal.rotate(rotation);
+        out.x += normal.x * 2.0 * dist;
+        out.y += normal.y * 2.0 * dist;
+    }
+    (out, rotation)
+}
+
+#[derive(PartialEq)]
+struct Open {
+    estimate: f32,
+    node: usize,
+}
+
+impl Eq for Open {}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Open) -> Ordering {
+        //Reversed for the heap to pop the lowest estimate first
+        other.estimate.partial_cmp(&self.estimate).unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Open) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+struct Graph<'a> {
+    map: &'a Map,
+    options: &'a NavOptions,
+    portals: Vec<(&'a Portal, &'a Portal)>,
+}
+
+impl<'a> Graph<'a> {
+    fn node(&self, pos: Vector3f) -> Option<usize> {
+        let (x, y, z) = (pos.x.floor(), pos.y.floor(), (pos.z + EPSILON).floor());
+
+        if !self.map.contains(&Vector3f::new(x, y, z)) {
+            return None;
+        }
+        Some(((z as usize * self.map.height()) + y as usize) * self.map.width() + x as usize)
+    }
+
+    fn cell(&self, node: usize) -> (f32, f32, f32) {
+        let (width, height) = (self.map.width(), self.map.height());
+
+        ((node % width) as f32, (node / width % height) as f32, (node / width / height) as f32)
+    }
+
+    /// Lower bound of the cost between two cells, going through a portal or not
+    fn heuristic(&self, node: usize, goal: (f32, f32, f32)) -> f32 {
+        let (x, y, _) = self.cell(node);
+        let manhattan = |ax: f32, ay: f32, bx: f32, by: f32| (ax - bx).abs() + (ay - by).abs();
+
+        self.portals.iter().fold(manhattan(x, y, goal.0, goal.1), |best, (source, dest)| {
+            let through = manhattan(x, y, source.pos.x, source.pos.y) + (manhattan(dest.pos.x, dest.pos.y, goal.0, goal.1) - 1.0).max(0.0);
+
+            best.min(through)
+        })
+    }
+
+    /// Cells reachable in one step, with their height, their cost and the portal crossed
+    fn neighbours(&self, node: usize, height: f32) -> Vec<(usize, f32, f32, Option<Vector3f>)> {
+        let (x, y, _) = self.cell(node);
+        let center = Vector3f::new(x + 0.5, y + 0.5, height);
+        let mut next = Vec::with_capacity(NEIGHBOURS.len());
+
+        for &(dx, dy) in &NEIGHBOURS {
+            let column = Vector3f::new(x + dx as f32, y + dy as f32, (height + EPSILON).floor());
+
+            if let Some((Some(source), Some(dest))) = self.map.portals_at(column, Direction::None) {
+                let (out, _) = through_portal(source, dest, center);
+
+                if let Some(height) = stand_height(self.map, out, self.options) {
+                    if let Some(to) = self.node(Vector3f::new(out.x, out.y, height)) {
+                        next.push((to, height, 1.0, Some(Vector3f::new(column.x + 0.5, column.y + 0.5, center.z))));
+                    }
+                }
+                continue;
+            }
+            let pos = Vector3f::new(column.x + 0.5, column.y + 0.5, height);
+
+            if let Some(height) = stand_height(self.map, pos, self.options) {
+                let closed_door = match self.map.get(&column) {
+                    Cell::Thin(thin) => thin.value() >= DOOR_VALUE && thin.slide() > 0.0,
+                    _ => false,
+                };
+                let cost = if closed_door { 1.0 + self.options.door_cost } else { 1.0 };
+
+                if let Some(to) = self.node(Vector3f::new(pos.x, pos.y, height)) {
+                    next.push((to, height, cost, None));
+                }
+            }
+        }
+        next
+    }
+}
+
+/// Shortest walk between two positions with A*
+///
+/// Walkers go from cell to cell along the axes, climb or drop low walls, open doors
+/// and cross portals
+///
+/// # Arguments
+/// * map - Map walked in
+/// * from - Start, z being the height of the feet
+/// * to - Destination, z being the height of the feet
+/// * options - What the walker can go through
+///
+/// Return the waypoints after the start, empty when both are in the same cell, `None` when `to` cannot be reached
+pub fn find_path(map: &Map, from: Vector3f, to: Vector3f, options: &NavOptions) -> Option<Vec<Waypoint>> {
+    let graph = Graph { map, options, portals: map.linked_portals().collect() };
+    let (start, goal) = (graph.node(from)?, graph.node(to)?);
+    let goal_cell = graph.cell(goal);
+    let size = map.width() * map.height() * map.depth();
+    let mut cost = vec![f32::INFINITY; size];
+    let mut heights = vec![0.0; size];
+    let mut previous: Vec<Option<(usize, Option<Vector3f>)>> = vec![None; size];
+    let mut open = BinaryHeap::new();
+
+    cost[start] = 0.0;
+    heights[start] = from.z;
+    open.push(Open { estimate: graph.heuristic(start, goal_cell), node: start });
+    while let Some(Open { estimate, node }) = open.pop() {
+        if node == goal {
+            break;
+        }
+        if estimate > cost[node] + graph.heuristic(node, goal_cell) + EPSILON {
+            //Outdated entry, the node was reached for less since
+            continue;
+        }
+        for (next, height, step, portal) in graph.neighbours(node, heights[node]) {
+            let new_cost = cost[node] + step;
+
+            if new_cost < cost[next] {
+                cost[next] = new_cost;
+                heights[next] = height;
+                previous[next] = Some((node, portal));
+                open.push(Open { estimate: new_cost + graph.heuristic(next, goal_cell), node: next });
+            }
+        }
+    }
+    if cost[goal].is_infinite() {
+        return None;
+    }
+
+    let mut path = Vec::new();
+    let mut node = goal;
+
+    while let Some((from, portal)) = previous[node] {
+        let (x, y, _) = graph.cell(node);
+
+        path.push(Waypoint { pos: Vector3f::new(x + 0.5, y + 0.5, heights[node]), portal: false });
+        if let Some(entry) = portal {
+            path.push(Waypoint { pos: entry, portal: true });
+        }
+        node = from;
+    }
+    path.reverse();
+    Some(path)
+}
@@ -1,32 +1,128 @@
+use std::collections::BTreeSet;
+
+use serde_derive::Serialize;
+
 use super::map::Map;
-use super::cell::{Cell, Interaction};
+use super::cell::Cell;
+use super::event::Event;
 use super::vectors::{Vector2f, Vector3f};
+use super::weapon::Arsenal;
+use super::sprite::{Sprite, Pickup, PickupKind};
+use super::input::Action;
 use crate::engine::Direction;
 
-#[derive(Clone, Copy, Eq, PartialEq)]
-enum Action {
-    None = 0,
-    MoveForward = 90,
-    MoveForward2 = 87,
-    MoveBackward = 83,
-    LookLeft = 81,
-    LookLeft2 = 65,
-    LookRight = 68,
-    Jump = 32,
-    Interact = 70,
+/// Distance walked per second
+const WALK_SPEED: f32 = 4.0;
+/// Rotation per second (Radian)
+const TURN_SPEED: f32 = 3.5;
+/// Pitch change per second (Radian)
+const PITCH_SPEED: f32 = 1.5;
+/// Highest pitch up or down, the horizon then stays on the screen (Radian)
+pub const MAX_PITCH: f32 = 0.45;
+pub const MAX_HEALTH: u32 = 100;
+pub const MAX_ARMOR: u32 = 100;
+/// Height of the eyes above the feet
+const EYE_HEIGHT: f32 = 0.5;
+/// Height of the eyes lying dead
+const DEAD_EYE_HEIGHT: f32 = 0.1;
+/// Duration of the fall of the camera on death
+const DROP_TIME: f32 = 0.5;
+
+///
+/// State of the player shown by the host
+///
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Status {
+    pub health: u32,
+    pub armor: u32,
+    pub alive: bool,
+    /// Name of the weapon in hand
+    pub weapon: Option<String>,
+    /// Ammunition left for the weapon in hand, `None` for melee weapons
+    pub ammo: Option<u32>,
+    pub keys: Vec<String>,
+    pub treasure: u32,
+}
+
+/// Movement actions held down and analog sticks, opposite ones cancelling each other
+#[derive(Clone, Copy, Default)]
+struct Held {
+    forward: bool,
+    backward: bool,
+    strafe_left: bool,
+    strafe_right: bool,
+    look_left: bool,
+    look_right: bool,
+    look_up: bool,
+    look_down: bool,
+    /// Walking stick, x forward and y to the right
+    stick: Vector2f,
+    /// Looking stick, x to the right and y upward
+    look: Vector2f,
+}
+
+impl Held {
+    /// Walking direction relative to the view, x forward and y to the right, of length up to 1
+    fn walk(&self) -> Vector2f {
+        let axis = |positive: bool, negative: bool| positive as i32 as f32 - negative as i32 as f32;
+        let walk = Vector2f::new(axis(self.forward, self.backward) + self.stick.x, axis(self.strafe_right, self.strafe_left) + self.stick.y);
+        let length = walk.length();
+
+        //Diagonals are not faster, slight pushes of the stick are slower
+        if length > 1.0 {
+            Vector2f::new(walk.x / length, walk.y / length)
+        } else {
+            walk
+        }
+    }
+
+    /// Turning speed, from -1 to 1 to the right
+    fn turn(&self) -> f32 {
+        (self.look_right as i32 as f32 - self.look_left as i32 as f32 + self.look.x).clamp(-1.0, 1.0)
+    }
+
+    /// Pitching speed, from -1 to 1 upward
+    fn tilt(&self) -> f32 {
+        (self.look_up as i32 as f32 - self.look_down as i32 as f32 + self.look.y).clamp(-1.0, 1.0)
+    }
+}
+
+/// Pose the player respawns in
+#[derive(Clone, Copy)]
+struct Spawn {
+    pos: Vector3f,
+    dir: Vector2f,
+    plane: Vector2f,
 }
 
 pub struct Player {
     pub pos: Vector3f,
     pub dir: Vector2f,
     pub plane: Vector2f,
+    /// Vertical angle of the view, positive looking up (Radian)
+    pub pitch: f32,
     pub frame: u32,
+    pub weapons: Arsenal,
+    pub health: u32,
+    /// Absorbs half of the damage until it runs out
+    pub armor: u32,
+    /// Keys held, by ID
+    pub keys: BTreeSet<String>,
+    /// Points of the treasures collected
+    pub treasure: u32,
 
+    /// Time since the death
+    death: Option<f32>,
+    spawn: Spawn,
+    /// Hazard damage not taken yet, below one point
+    burn: f32,
     delta: f32,
     gravity: f32,
-    velocity: Vector2f,
-    rotation: Vector2f,
-    action: Action,
+    /// Vertical speed
+    velocity: f32,
+    held: Held,
+    /// Whether Interact was triggered since the last update
+    interact: bool,
 }
 
 impl Player {
@@ -35,54 +131,230 @@ impl Player {
             pos,
             dir: Vector2f::new( 1.0, 0.0),
             plane: Vector2f::new(0.0, 0.66),
+            pitch: 0.0,
             frame: 0,
+            weapons: Arsenal::default(),
+            health: MAX_HEALTH,
+            armor: 0,
+            keys: BTreeSet::new(),
+            treasure: 0,
+            death: None,
+            spawn: Spawn { pos, dir: Vector2f::default(), plane: Vector2f::default() },
+            burn: 0.0,
             delta: 0.0,
             gravity: -3.8,
-            velocity: Vector2f::default(),
-            rotation: Vector2f::default(),
-            action: Action::None,
+            velocity: 0.0,
+            held: Held::default(),
+            interact: false,
         };
         player.update_dir(std::f32::consts::PI / 2.0, 1.0);
+        player.spawn = Spawn { pos, dir: player.dir, plane: player.plane };
         player
     }
 
-    pub fn handle_inputs(&mut self, key: u32, pressed: bool) {
-        if key == Action::MoveForward as u32 || key == Action::MoveForward2 as u32 {
-            self.velocity.x = if pressed { 4.0 } else { 0.0 }
-        } else if key == Action::MoveBackward as u32 {
-            self.velocity.x = if pressed { -4.0 } else { 0.0 }
-        } else if key == Action::Jump as u32 {
-            if pressed && self.velocity.y == 0.0 {
-                self.velocity.y = 1.65;
+    pub fn is_alive(&self) -> bool {
+        self.death.is_none()
+    }
+
+    /// Position of the eyes, dropping to the ground once dead
+    pub fn eye(&self) -> Vector3f {
+        let height = match self.death {
+            Some(time) => EYE_HEIGHT + (DEAD_EYE_HEIGHT - EYE_HEIGHT) * (time / DROP_TIME).min(1.0),
+            None => EYE_HEIGHT,
+        };
+
+        Vector3f::new(self.pos.x, self.pos.y, self.pos.z + height)
+    }
+
+    /// Vertical shift of the view, added to the slope of every row
+    ///
+    /// The rows keep their screen spacing: looking up moves the horizon down instead of tilting the walls
+    pub fn shear(&self) -> f32 {
+        self.pitch.tan()
+    }
+
+    pub fn status(&self) -> Status {
+        let weapon = self.weapons.current();
+
+        Status {
+            health: self.health,
+            armor: self.armor,
+            alive: self.is_alive(),
+            weapon: weapon.map(|weapon| weapon.name.clone()),
+            ammo: weapon.and_then(|weapon| weapon.ammo.as_ref()).map(|ammo| self.weapons.ammo(ammo)),
+            keys: self.keys.iter().cloned().collect(),
+            treasure: self.treasure,
+        }
+    }
+
+    /// Take an item, unless it would be wasted
+    ///
+    /// # Arguments
+    /// * pickup - Item lying in the map, weapons being known by the arsenal
+    ///
+    /// Return whether the item is taken
+    pub fn collect(&mut self, pickup: &Pickup) -> bool {
+        if !self.is_alive() {
+            return false;
+        }
+        let item = pickup.item.as_deref().unwrap_or_default();
+
+        match pickup.kind {
+            PickupKind::Health if self.health < MAX_HEALTH => self.health = (self.health + pickup.amount).min(MAX_HEALTH),
+            PickupKind::Armor if self.armor < MAX_ARMOR => self.armor = (self.armor + pickup.amount).min(MAX_ARMOR),
+            PickupKind::Health | PickupKind::Armor => return false,
+            PickupKind::Ammo => self.weapons.add_ammo(item, pickup.amount),
+            PickupKind::Weapon => match self.weapons.find(item) {
+                Some(weapon) => self.weapons.give(weapon, pickup.amount),
+                None => return false,
+            },
+            PickupKind::Key => {
+                self.keys.insert(item.to_string());
+            }
+            PickupKind::Treasure => self.treasure += pickup.amount,
+        }
+        true
+    }
+
+    /// Hurt the player, the armor taking half of the damage while it lasts
+    ///
+    /// # Arguments
+    /// * damage - Health lost without armor
+    ///
+    /// Return the event of the wound or of the death
+    pub fn hurt(&mut self, damage: u32) -> Option<Event> {
+        if !self.is_alive() || damage == 0 {
+            return None;
+        }
+        let absorbed = (damage / 2).min(self.armor);
+        let damage = damage - absorbed;
+
+        self.armor -= absorbed;
+        self.health = self.health.saturating_sub(damage);
+        if self.health == 0 {
+            self.death = Some(0.0);
+            return Some(Event::Died);
+        }
+        Some(Event::Hurt { damage })
+    }
+
+    /// Bring the player back to life at the last checkpoint, or where the map starts
+    pub fn respawn(&mut self) {
+        self.pos = self.spawn.pos;
+        self.dir = self.spawn.dir;
+        self.plane = self.spawn.plane;
+        self.pitch = 0.0;
+        self.velocity = 0.0;
+        self.health = MAX_HEALTH;
+        self.armor = 0;
+        self.death = None;
+        self.burn = 0.0;
+    }
+
+    /// Take over the health, armor, treasure, weapons and ammunition of the player of the previous level
+    ///
+    /// Keys open the doors of their own level and are left behind, a dead player comes back with full health and no armor like a respawn
+    pub fn carry(&mut self, previous: &Player) {
+        if previous.is_alive() {
+            self.health = previous.health;
+            self.armor = previous.armor;
+        }
+        self.treasure = previous.treasure;
+        self.weapons.carry(&previous.weapons);
+    }
+
+    /// Move the camera
+    ///
+    /// # Arguments
+    /// * pos - New position, z being the floor level
+    /// * angle - Yaw (Radian), 0 looks toward +x
+    pub fn set_pose(&mut self, pos: Vector3f, angle: f32) {
+        self.pos = pos;
+        self.pitch = 0.0;
+        self.dir = Vector2f::new(1.0, 0.0);
+        self.plane = Vector2f::new(0.0, 0.66);
+        self.update_dir(angle, 1.0);
+    }
+
+    /// Turn the view, unless dead
+    ///
+    /// # Arguments
+    /// * angle - Rotation to the right (Radian)
+    pub fn turn(&mut self, angle: f32) {
+        if self.is_alive() {
+            self.update_dir(angle, 1.0);
+        }
+    }
+
+    /// Look up or down, unless dead, up to [`MAX_PITCH`]
+    ///
+    /// # Arguments
+    /// * angle - Rotation upward (Radian)
+    pub fn tilt(&mut self, angle: f32) {
+        if self.is_alive() {
+            self.pitch = (self.pitch + angle).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+    }
+
+    /// Walk and look at a share of the full speed, e.g. with the sticks of a gamepad
+    ///
+    /// # Arguments
+    /// * walk - Walking direction, x forward and y to the right, of length up to 1
+    /// * look - Turning and pitching speed, x to the right and y upward, from -1 to 1
+    pub fn steer(&mut self, walk: Vector2f, look: Vector2f) {
+        self.held.stick = walk;
+        self.held.look = look;
+    }
+
+    /// Start or stop an action
+    ///
+    /// # Arguments
+    /// * action - Action bound to the key
+    /// * pressed - Whether the key went down
+    pub fn handle_action(&mut self, action: Action, pressed: bool) {
+        match action {
+            Action::MoveForward => self.held.forward = pressed,
+            Action::MoveBackward => self.held.backward = pressed,
+            Action::StrafeLeft => self.held.strafe_left = pressed,
+            Action::StrafeRight => self.held.strafe_right = pressed,
+            Action::Jump => {
+                if pressed && self.velocity == 0.0 {
+                    self.velocity = 1.65;
+                }
+            }
+            Action::LookLeft => self.held.look_left = pressed,
+            Action::LookRight => self.held.look_right = pressed,
+            Action::LookUp => self.held.look_up = pressed,
+            Action::LookDown => self.held.look_down = pressed,
+            Action::Interact => self.interact |= pressed,
+            Action::Fire => self.weapons.pull(pressed),
+            _ => {
+                if let Some(weapon) = action.weapon().filter(|_| pressed) {
+                    self.weapons.select(weapon);
+                }
             }
-        } else if key == Action::LookLeft as u32 || key == Action::LookLeft2 as u32 {
-            self.rotation.x = if pressed { -3.5 } else { 0.0 }
-        } else if key == Action::LookRight as u32 {
-            self.rotation.x = if pressed { 3.5 } else { 0.0 }
-        } else if key == Action::Interact as u32 {
-            self.action = Action::Interact;
         }
     }
 
     fn update_gravity(&mut self, map: &Map, delta: f32) {
-        let mut future_z = self.pos.z + self.velocity.y * delta;
+        let mut future_z = self.pos.z + self.velocity * delta;
         let inside_wall = map.get(&self.pos);
         let future_under_wall = map.get(&Vector3f::new(self.pos.x, self.pos.y, future_z));
 
-        self.velocity.y += self.gravity * delta;
+        self.velocity += self.gravity * delta;
         self.pos.z = if future_z < 0.0 {
-            self.velocity.y = 0.0;
+            self.velocity = 0.0;
             0.0
         } else {
             if let Cell::Wall {value: _, height} = inside_wall {
                 if future_z <= self.pos.z.floor() + *height {
                     future_z = self.pos.z.floor() + *height;
-                    self.velocity.y = 0.0;
+                    self.velocity = 0.0;
                 }
             } else if let Cell::Empty = inside_wall {
                 if future_z <= future_z.floor() + future_under_wall.height() {
                     future_z = future_z.floor() + future_under_wall.height();
-                    self.velocity.y = 0.0;
+                    self.velocity = 0.0;
                 }
             }
             future_z
@@ -117,13 +389,20 @@ impl Player {
         }
     }
 
-    fn update_pos(&mut self, map: &mut Map, delta: f32) {
-        let speed = self.velocity.x * delta;
-        let new_x = self.pos.x + self.dir.x * speed;
-        let new_y = self.pos.y + self.dir.y * speed;
+    /// Move the player, sliding along the walls and the solid sprites, and crossing the portals
+    ///
+    /// # Arguments
+    /// * map - Map to walk in
+    /// * sprites - Sprites, the solid ones blocking the way
+    /// * step - Move in the map
+    fn update_pos(&mut self, map: &mut Map, sprites: &[Sprite], mut step: Vector2f) {
+        let new_x = self.pos.x + step.x;
+        let new_y = self.pos.y + step.y;
 
         match map.portals_at(Vector3f::new(new_x.floor(), new_y.floor(), self.pos.z.floor()), Direction::None) {
             None => {
+                let start = self.pos;
+
                 match map.get(&Vector3f::new(new_x, self.pos.y, self.pos.z)) {
                     Cell::Empty => {
                         match map.get(&self.pos) {
@@ -138,6 +417,10 @@ impl Player {
                     }
                     Cell::Thin(thin) => self.move_x_in_thin_wall(new_x.floor(), new_x, thin.dir(), thin.slide(), thin.depth())
                 }
+                //Solid sprites stop each axis on its own, sliding along them
+                if Sprite::blocks(sprites, start, self.pos, 0.0) {
+                    self.pos.x = start.x;
+                }
 
                 match map.get(&Vector3f::new(self.pos.x, new_y, self.pos.z)) {
                     Cell::Empty => {
@@ -153,15 +436,21 @@ impl Player {
                     }
                     Cell::Thin(thin) => self.move_y_in_thin_wall(new_y.floor(), new_y, thin.dir(), thin.slide(), thin.depth())
                 }
+                if Sprite::blocks(sprites, Vector3f::new(self.pos.x, start.y, start.z), self.pos, 0.0) {
+                    self.pos.y = start.y;
+                }
             }
             Some((first, second)) => {
                 let source = first.unwrap();
                 let dest = second.unwrap();
 
                 let tmp = self.pos;
-                self.update_dir(dest.link_dir(source), 1.0);
-                self.pos.x = dest.link_x(source, &tmp) + self.dir.x * speed;
-                self.pos.y = dest.link_y(source, &tmp) + self.dir.y * speed;
+                let rotation = dest.link_dir(source);
+
+                self.update_dir(rotation, 1.0);
+                step.rotate(rotation);
+                self.pos.x = dest.link_x(source, &tmp) + step.x;
+                self.pos.y = dest.link_y(source, &tmp) + step.y;
                 self.pos.z = dest.pos.z;
             }
         }
@@ -173,33 +462,89 @@ impl Player {
         self.plane.rotate(new_rotation);
     }
 
-    pub fn update(&mut self, map: &mut Map, delta: f32) {
-        if self.action == Action::Interact {
-            let hit = crate::engine::rayobject::Ray::new(&self, Vector2f::default()).cast(map);
+    /// Move the player, and hurt it when it stands in a hazard
+    ///
+    /// Dead, the view is frozen until the Interact action respawns it
+    ///
+    /// # Arguments
+    /// * map - Map to walk in
+    /// * sprites - Sprites, the solid ones blocking the way
+    /// * occupants - Positions a pushed wall waits for to be left
+    /// * delta - Elapsed time (Second)
+    ///
+    /// Return what happened to the player
+    pub fn update(&mut self, map: &mut Map, sprites: &[Sprite], occupants: &[Vector3f], delta: f32) -> Vec<Event> {
+        let mut events = Vec::new();
+
+        if let Some(time) = self.death {
+            self.death = Some(time + delta);
+            if self.interact && time + delta >= DROP_TIME {
+                self.respawn();
+                events.push(Event::Respawned);
+            }
+            self.interact = false;
+            if self.death.is_some() && (self.velocity != 0.0 || self.pos.z > 0.0) {
+                self.update_gravity(map, delta);
+            }
+            return events;
+        }
+        if self.interact {
+            let hit = crate::engine::rayobject::Ray::new(self, Vector2f::default()).cast(map);
 
-            if let Some(_) = hit.value {
-                if hit.dist <= 1.5 {
-                    map.get_mut(&hit.pos).trigger();
+            if hit.value.is_some() && hit.dist <= 1.5 {
+                if map.is_exit(&hit.pos) {
+                    events.push(Event::Exited);
+                } else {
+                    events.extend(map.trigger(&hit.pos, hit.dir, &self.keys, occupants));
                 }
             }
-            self.action = Action::None;
+            self.interact = false;
         }
-        if self.velocity.y != 0.0 || self.pos.z > 0.0 {
+        if self.velocity != 0.0 || self.pos.z > 0.0 {
             self.update_gravity(map, delta);
         }
-        if self.velocity.x != 0.0 {
-            self.update_pos(map, delta);
+        let walk = self.held.walk();
+
+        if walk.x != 0.0 || walk.y != 0.0 {
+            //Forward along the view, right along the camera plane
+            let right = Vector2f::new(self.plane.x / self.plane.length(), self.plane.y / self.plane.length());
+            let speed = WALK_SPEED * delta;
+            let step = Vector2f::new((self.dir.x * walk.x + right.x * walk.y) * speed, (self.dir.y * walk.x + right.y * walk.y) * speed);
+
+            self.update_pos(map, sprites, step);
             self.delta += delta;
             if self.delta > 0.16 {
-                self.frame -= 1;
-                self.frame = (self.frame + 1) % 4 + 1;
+                //Walking frames go from 1 to 4, 0 standing still
+                self.frame = self.frame % 4 + 1;
                 self.delta = 0.0;
             }
         } else {
             self.frame = 0;
         }
-        if self.rotation.x != 0.0 {
-            self.update_dir(self.rotation.x, delta);
+        if self.held.turn() != 0.0 {
+            self.update_dir(self.held.turn() * TURN_SPEED, delta);
+        }
+        self.tilt(self.held.tilt() * PITCH_SPEED * delta);
+
+        let hazard = map.hazard(&self.pos);
+
+        self.burn = if hazard > 0.0 { self.burn + hazard * delta } else { 0.0 };
+        if self.burn >= 1.0 {
+            let damage = self.burn.floor();
+
+            self.burn -= damage;
+            events.extend(self.hurt(damage as u32));
+        }
+        let cell = Vector3f::new(self.pos.x.floor(), self.pos.y.floor(), self.pos.z.floor());
+        let spawn_cell = Vector3f::new(self.spawn.pos.x.floor(), self.spawn.pos.y.floor(), self.spawn.pos.z.floor());
+
+        if self.is_alive() && map.is_checkpoint(&self.pos) && cell != spawn_cell {
+            self.spawn = Spawn { pos: Vector3f::new(cell.x + 0.5, cell.y + 0.5, self.pos.z), dir: self.dir, plane: self.plane };
+            events.push(Event::Checkpoint);
+        }
+        if self.is_alive() && map.is_exit(&self.pos) {
+            events.push(Event::Exited);
         }
+        events
     }
 }
\ No newline at end of file
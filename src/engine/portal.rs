@@ -1,5 +1,6 @@
 use super::vectors::Vector3f;
 use super::direction::Direction;
+use super::schema::PortalData;
 
 use crate::graphics::HSLColor;
 
@@ -10,17 +11,8 @@ pub struct Portal {
 }
 
 impl Portal {
-    pub fn from_json(json: &serde_json::Value) -> Option<Portal> {
-        if let Some(portal) = json.as_object() {
-            let x = portal["pos"]["x"].as_u64().unwrap() as f32;
-            let y = portal["pos"]["y"].as_u64().unwrap() as f32;
-            let z = portal["pos"]["z"].as_u64().unwrap() as f32;
-            let hue = portal["hue"].as_u64().unwrap() as f32;
-            let dir = Direction::from_str(portal["direction"].as_str().unwrap());
-
-            return Some(Portal { pos: Vector3f::new(x, y, z), dir, hsl: HSLColor::new(hue, 0.0, 0.0) });
-        }
-        None
+    pub fn from_data(data: &PortalData) -> Portal {
+        Portal { pos: Vector3f::from(data.pos), dir: Direction::from(data.direction), hsl: HSLColor::new(data.hue, 0.0, 0.0) }
     }
 
     pub fn link_dir(&self, rhs: &Portal) -> f32 {
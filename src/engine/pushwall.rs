@@ -0,0 +1,87 @@
+use super::vectors::Vector3f;
+use super::direction::Direction;
+
+/// Motion of a pushwall
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Never pushed, still a secret
+    Resting,
+    /// Leaving its cell toward a direction
+    Sliding(Direction),
+    /// Done sliding, a plain wall from now on
+    Stopped,
+}
+
+///
+/// Secret wall block
+///
+/// Pushed once, it slides away from the player cell by cell, until it went its distance or hit a wall
+///
+#[derive(Clone)]
+pub struct Pushwall {
+    /// Cell the block rests in or leaves
+    pub pos: Vector3f,
+    pub value: u32,
+    /// Cells left to slide, `None` when it only stops on a wall
+    left: Option<u32>,
+    state: State,
+}
+
+impl Pushwall {
+    pub fn new(pos: Vector3f, value: u32, distance: Option<u32>) -> Pushwall {
+        Pushwall { pos, value, left: distance, state: State::Resting }
+    }
+
+    /// Whether the player found the secret
+    pub fn is_found(&self) -> bool {
+        self.state != State::Resting
+    }
+
+    /// Direction of the slide, `None` when the block does not move
+    pub fn sliding(&self) -> Option<Direction> {
+        match self.state {
+            State::Sliding(dir) => Some(dir),
+            _ => None,
+        }
+    }
+
+    /// Cell the block enters, `None` when it does not move
+    pub fn next(&self) -> Option<Vector3f> {
+        let (x, y) = match self.sliding()? {
+            Direction::North => (0.0, 1.0),
+            Direction::South => (0.0, -1.0),
+            Direction::East => (1.0, 0.0),
+            Direction::West => (-1.0, 0.0),
+            Direction::None | Direction::Up | Direction::Down => return None,
+        };
+
+        Some(Vector3f::new(self.pos.x + x, self.pos.y + y, self.pos.z))
+    }
+
+    /// Start sliding toward a direction, the one of the ray hitting the block
+    ///
+    /// Return whether the block was resting on a side
+    pub fn push(&mut self, dir: Direction) -> bool {
+        if self.state != State::Resting || !dir.is_side() || dir == Direction::None {
+            return false;
+        }
+        self.state = State::Sliding(dir);
+        true
+    }
+
+    /// Enter the next cell, once the block left the current one
+    ///
+    /// Return whether the block should go on sliding
+    pub fn advance(&mut self) -> bool {
+        if let Some(next) = self.next() {
+            self.pos = next;
+            self.left = self.left.map(|left| left - 1);
+        }
+        self.left != Some(0)
+    }
+
+    /// Stop sliding, for good
+    pub fn stop(&mut self) {
+        self.state = State::Stopped;
+    }
+}
@@ -5,7 +5,9 @@ use super::portal::Portal;
 use super::cell::{Cell, Thin, DOOR_VALUE};
 use super::player::Player;
 
-static PORTAL_RECURSION_LIMIT: usize = 3;
+pub static PORTAL_RECURSION_LIMIT: usize = 3;
+/// Horizontal distance after which rays stop
+pub static MAX_DIST: f32 = 30.0;
 
 pub struct Ray {
     pos: Vector3f,
@@ -27,10 +29,29 @@ pub struct Hit {
 }
 
 impl Ray {
+    /// Construct a ray through a point of the screen, sheared by the pitch of the player
+    ///
+    /// # Arguments
+    /// * player - Camera
+    /// * camera_dir - Point of the screen, from -1 to 1 left to right and top to bottom
+    ///
+    /// Return Ray object
     pub fn new(player: &Player, camera_dir: Vector2f) -> Ray {
-        let mut origin = player.pos;
+        let dir = Vector3f::new(player.dir.x + player.plane.x * camera_dir.x, player.dir.y + player.plane.y * camera_dir.x, 0.5 * camera_dir.y - player.shear());
+
+        Ray::looking(player.pos, dir)
+    }
+
+    /// Construct a ray leaving a position at eye height
+    ///
+    /// # Arguments
+    /// * from - Start, z being the floor level
+    /// * dir - Direction, the distances of the hits are expressed in its length
+    ///
+    /// Return Ray object
+    pub fn looking(from: Vector3f, dir: Vector3f) -> Ray {
+        let mut origin = from;
         let pos = Vector3f::new(origin.x.floor(), origin.y.floor(), origin.z.round());
-        let dir = Vector3f::new(player.dir.x + player.plane.x * camera_dir.x, player.dir.y + player.plane.y * camera_dir.x, 0.5 * camera_dir.y);
         let delta = Vector3f::new((1.0 / dir.x).abs(), (1.0 / dir.y).abs(), (1.0 / dir.z).abs());
         let mut step = Vector3f::default();
         let mut side_dist = Vector3f::default();
@@ -57,6 +78,81 @@ impl Ray {
         }
     }
 
+    /// Construct a ray with no vertical component
+    ///
+    /// Used to walk the cells seen by a whole screen column: each cell is
+    /// entered at [`entry_dist`](Ray::entry_dist) and left at [`exit_dist`](Ray::exit_dist)
+    ///
+    /// # Arguments
+    /// * origin - Camera position, z being the eye height
+    /// * dir - Direction of the column
+    ///
+    /// Return Ray object
+    pub fn horizontal(origin: Vector3f, dir: Vector2f) -> Ray {
+        let pos = Vector3f::new(origin.x.floor(), origin.y.floor(), origin.z.floor());
+        let ray_dir = Vector3f::new(dir.x, dir.y, 0.0);
+        let delta = Vector3f::new((1.0 / dir.x).abs(), (1.0 / dir.y).abs(), f32::INFINITY);
+        let mut step = Vector3f::default();
+        let mut side_dist = Vector3f::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+
+        step.x = if dir.x < 0.0 { -1.0 } else { 1.0 };
+        step.y = if dir.y < 0.0 { -1.0 } else { 1.0 };
+        if dir.x != 0.0 {
+            side_dist.x = if dir.x < 0.0 { origin.x - pos.x } else { pos.x + 1.0 - origin.x } * delta.x;
+        }
+        if dir.y != 0.0 {
+            side_dist.y = if dir.y < 0.0 { origin.y - pos.y } else { pos.y + 1.0 - origin.y } * delta.y;
+        }
+
+        Ray {
+            pos,
+            ray_dir,
+            delta,
+            step,
+            side_dist,
+            origin,
+            portal_recursion: 0,
+            dir: Direction::default(),
+        }
+    }
+
+    /// Cell the ray is in
+    pub fn cell(&self) -> Vector3f {
+        self.pos
+    }
+
+    /// Direction of the ray, turned by the portals crossed
+    pub fn direction(&self) -> Vector3f {
+        self.ray_dir
+    }
+
+    /// Side through which the ray entered its cell, [`Direction::None`] for the first one
+    pub fn side(&self) -> Direction {
+        self.dir
+    }
+
+    /// Distance at which the ray entered its cell
+    pub fn entry_dist(&self) -> f32 {
+        self.compute_dist()
+    }
+
+    /// Distance at which the ray leaves its cell horizontally
+    pub fn exit_dist(&self) -> f32 {
+        self.side_dist.x.min(self.side_dist.y)
+    }
+
+    /// Horizontal distance between the origin and the cell corner
+    pub fn cell_dist(&self) -> f32 {
+        ((self.pos.x - self.origin.x).powf(2.0) + (self.pos.y - self.origin.y).powf(2.0)).sqrt()
+    }
+
+    /// Intersection with the thin wall of the current cell
+    ///
+    /// Return the distance of the hit and the texture x coordinate
+    pub fn thin_hit(&self, cell: &dyn Thin) -> Option<(f32, f32)> {
+        self.grow_thin(cell).map(|(dist, texture_pos)| (dist, texture_pos.x))
+    }
+
     fn relocate(&mut self, new_origin: Vector3f, new_dir: f32) {
         let old_dir = self.ray_dir.x;
 
@@ -96,7 +192,6 @@ impl Ray {
     }
 
     pub fn cast(&mut self, map: &Map) -> Hit {
-        let max_dist = 30.0;
         let mut passed_door = false;
         let mut passed_dor_pos = Vector3f::default();
         let mut passed_height = 1.0;
@@ -124,7 +219,7 @@ impl Ray {
 
             match map.get(&self.pos) {
                 Cell::Empty => {
-                    if self.pos.z < 0.0 || self.pos.z >= map.depth() as f32 || ((self.pos.x - self.origin.x).powf(2.0) + (self.pos.y - self.origin.y).powf(2.0)).sqrt() > max_dist {
+                    if self.pos.z < 0.0 || self.pos.z >= map.depth() as f32 || self.cell_dist() > MAX_DIST {
                         let dist = self.compute_dist();
 
                         return Hit { value: None, pos: self.pos, dist, dir: self.dir, texture_pos: Vector2f::default() };
@@ -154,7 +249,7 @@ impl Ray {
                     }
                 }
                 Cell::Thin(thin) => {
-                    if let Some((dist, texture_pos)) = self.grow_thin(thin) {
+                    if let Some((dist, texture_pos)) = self.grow_thin(thin.as_ref()) {
                         return Hit { value: Some(thin.value()), pos: self.pos, dist, dir: thin.dir(), texture_pos };
                     }
                     if thin.value() >= DOOR_VALUE {
@@ -201,7 +296,7 @@ impl Ray {
         }
     }
 
-    fn grow_thin(&mut self, cell: &Box<dyn Thin>) -> Option<(f32, Vector2f)> {
+    fn grow_thin(&self, cell: &dyn Thin) -> Option<(f32, Vector2f)> {
         let dir = cell.dir();
         let slide = cell.slide();
         let mut depth = cell.depth();
@@ -228,7 +323,7 @@ impl Ray {
             let offset = if self.origin.y >= self.pos.y && self.origin.y <= self.pos.y + depth {
                 //Player is inside
                 let relative_y = self.origin.y - self.origin.y.floor();
-                if facing_north && relative_y > depth || facing_south && relative_y > depth {
+                if (facing_north || facing_south) && relative_y > depth {
                     return None;
                 }
                 if depth > 0.5 && self.step.y < 0.0 {
@@ -252,7 +347,7 @@ impl Ray {
             let offset = if self.origin.x >= self.pos.x && self.origin.x <= self.pos.x + depth {
                 //Player is inside
                 let relative_x = self.origin.x - self.origin.x.floor();
-                if facing_west && relative_x > depth || facing_east && relative_x > depth {
+                if (facing_west || facing_east) && relative_x > depth {
                     return None;
                 }
                 if depth > 0.5 && self.step.x < 0.0 {
@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::de::IgnoredAny;
+use serde_derive::Deserialize;
+
+use super::direction::Direction;
+use super::sprite::PickupKind;
+use super::vectors::Vector3f;
+
+///
+/// Map loading error
+///
+/// Carries the JSON path of the faulty field, e.g. `cells[1][42].direction`
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapError {
+    /// JSON path of the faulty field
+    pub path: String,
+    /// What is wrong with it
+    pub message: String,
+}
+
+impl MapError {
+    pub fn new(path: impl Into<String>, message: impl Into<String>) -> MapError {
+        MapError {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl std::error::Error for MapError {}
+
+/// Maps of an episode, played in order
+#[derive(Deserialize)]
+pub struct CampaignData {
+    /// Map descriptions, see [`MapData`]
+    pub maps: Vec<serde_json::Value>,
+}
+
+impl CampaignData {
+    /// Deserialize a campaign, reporting the path of the first invalid field
+    ///
+    /// # Arguments
+    /// * json - Campaign description
+    ///
+    /// Return the typed campaign or the error
+    pub fn from_json(json: &serde_json::Value) -> Result<CampaignData, MapError> {
+        let campaign: CampaignData = serde_path_to_error::deserialize(json).map_err(|err| {
+            MapError::new(err.path().to_string(), err.inner().to_string())
+        })?;
+
+        if campaign.maps.is_empty() {
+            return Err(MapError::new("maps", "a campaign needs a map"));
+        }
+        Ok(campaign)
+    }
+}
+
+/// Whole map description, as exported by the editor
+#[derive(Deserialize)]
+pub struct MapData {
+    /// Number of cells along x, inferred from the cells when missing
+    pub width: Option<usize>,
+    /// Number of cells along y, inferred from the cells when missing
+    pub height: Option<usize>,
+    /// Number of floors, inferred from the cells when missing
+    pub floors: Option<usize>,
+    /// Cells of each floor, from the ground up
+    pub cells: Vec<Vec<CellData>>,
+    /// Default floor and ceiling textures of each floor, from the ground up
+    #[serde(default)]
+    pub surfaces: Vec<SurfaceData>,
+    /// Distance fog of the whole map
+    pub fog: Option<FogData>,
+    /// Fog of each floor, from the ground up, `null` keeps the one of the map
+    #[serde(default)]
+    pub floor_fogs: Vec<Option<FogData>>,
+    #[serde(default)]
+    pub portals: Vec<PortalPairData>,
+    #[serde(default)]
+    pub sprites: Vec<SpriteData>,
+    #[serde(default)]
+    pub enemies: Vec<EnemyData>,
+    /// Weapons of the player, the ones of the original game when missing
+    pub weapons: Option<Vec<WeaponData>>,
+    /// Ammunition the player starts with, by type
+    #[serde(default = "default_ammo")]
+    pub ammo: HashMap<String, u32>,
+    /// Time to beat, shown at the end of the level (Second)
+    pub par_time: Option<f32>,
+}
+
+fn default_ammo() -> HashMap<String, u32> {
+    std::iter::once(("bullets".to_string(), 8)).collect()
+}
+
+impl MapData {
+    /// Deserialize a map, reporting the path of the first invalid field
+    ///
+    /// # Arguments
+    /// * json - Map description
+    ///
+    /// Return the typed map or the error
+    pub fn from_json(json: &serde_json::Value) -> Result<MapData, MapError> {
+        serde_path_to_error::deserialize(json).map_err(|err| {
+            MapError::new(err.path().to_string(), err.inner().to_string())
+        })
+    }
+}
+
+/// Position of a cell in its floor
+#[derive(Deserialize, Clone, Copy)]
+pub struct CellPos {
+    pub x: usize,
+    pub y: usize,
+}
+
+#[derive(Deserialize)]
+pub struct CellData {
+    pub pos: CellPos,
+    /// Wall texture, missing on empty cells which only override their surfaces
+    pub value: Option<u32>,
+    /// Present on thin walls and doors, whatever its value
+    pub thin: Option<IgnoredAny>,
+    pub direction: Option<SideData>,
+    /// Whether the wall is a secret block sliding away from the player
+    #[serde(default)]
+    pub pushable: bool,
+    /// Number of cells a pushwall slides, until it hits a wall when missing
+    pub push_distance: Option<u32>,
+    /// ID of the key opening a door, e.g. "gold" or "silver"
+    pub lock: Option<String>,
+    pub height: Option<f32>,
+    /// Floor texture, overriding the one of the floor
+    pub floor: Option<u32>,
+    /// Ceiling texture, overriding the one of the floor
+    pub ceiling: Option<u32>,
+    /// Health lost per second by the player standing in the cell
+    #[serde(default)]
+    pub hazard: f32,
+    /// Whether the player respawns in the cell once it went through
+    #[serde(default)]
+    pub checkpoint: bool,
+    /// Whether the cell ends the level, walked into when empty or switched with Interact when a wall
+    #[serde(default)]
+    pub exit: bool,
+}
+
+/// Floor and ceiling textures, untextured when missing
+#[derive(Deserialize, Default)]
+pub struct SurfaceData {
+    pub floor: Option<u32>,
+    pub ceiling: Option<u32>,
+}
+
+/// Distance fog, a black one attenuates the light
+#[derive(Deserialize)]
+pub struct FogData {
+    /// Red, green and blue components
+    pub color: [u8; 3],
+    /// Distance at which the fog appears
+    pub start: f32,
+    /// Distance at which nothing but the fog is visible
+    pub end: f32,
+    #[serde(default)]
+    pub falloff: FalloffData,
+}
+
+/// How the fog thickens between its start and its end
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum FalloffData {
+    #[default]
+    Linear,
+    Exponential,
+}
+
+#[derive(Deserialize)]
+pub struct PortalPairData {
+    #[serde(default)]
+    pub first: Option<PortalData>,
+    #[serde(default)]
+    pub second: Option<PortalData>,
+}
+
+#[derive(Deserialize)]
+pub struct PortalData {
+    pub pos: PortalPos,
+    pub hue: f32,
+    pub direction: SideData,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct PortalPos {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+}
+
+impl From<PortalPos> for Vector3f {
+    fn from(pos: PortalPos) -> Vector3f {
+        Vector3f::new(pos.x as f32, pos.y as f32, pos.z as f32)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SpriteData {
+    /// Sprite texture, shifted by one: 0 is the player start
+    pub index: u32,
+    pub pos: SpritePos,
+    /// Item collected by walking over the sprite
+    pub pickup: Option<PickupData>,
+    /// Whether the sprite blocks the player and the enemies, like a pillar or a barrel
+    #[serde(default)]
+    pub solid: bool,
+    /// Distance from the center of a solid sprite kept by the ones walking around it
+    pub radius: Option<f32>,
+}
+
+/// Item of a sprite
+#[derive(Deserialize)]
+pub struct PickupData {
+    pub kind: PickupKind,
+    /// Health, armor, ammunition or treasure points given
+    #[serde(default)]
+    pub amount: u32,
+    /// Ammunition type, weapon name or key ID, depending on the kind
+    pub item: Option<String>,
+    /// Distance from the player at which the item is collected
+    pub radius: Option<f32>,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct SpritePos {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+/// Enemy soldier
+#[derive(Deserialize)]
+pub struct EnemyData {
+    pub pos: SpritePos,
+    /// Where the enemy looks at first (Degree), 0 looks toward +x
+    #[serde(default)]
+    pub angle: f32,
+    /// Cells walked through in a loop until the player is seen, standing guard when empty
+    #[serde(default)]
+    pub patrol: Vec<CellPos>,
+    pub health: Option<u32>,
+}
+
+/// Hitscan weapon
+#[derive(Deserialize)]
+pub struct WeaponData {
+    pub name: String,
+    /// Shots per second while the trigger is held
+    pub fire_rate: f32,
+    /// Largest deviation of a shot from the center of the view (Degree)
+    #[serde(default)]
+    pub spread: f32,
+    /// Distance up to which shots hit
+    pub range: f32,
+    /// Health taken by a shot
+    pub damage: u32,
+    /// Type of ammunition used by a shot, none for melee weapons
+    pub ammo: Option<String>,
+    /// Whether the player has the weapon from the start
+    #[serde(default)]
+    pub owned: bool,
+}
+
+/// Side a thin wall or a portal faces
+#[derive(Deserialize, Clone, Copy)]
+pub enum SideData {
+    #[serde(alias = "North/South")]
+    North,
+    #[serde(alias = "East/West")]
+    West,
+    South,
+    East,
+}
+
+impl From<SideData> for Direction {
+    fn from(side: SideData) -> Direction {
+        match side {
+            SideData::North => Direction::North,
+            SideData::West => Direction::West,
+            SideData::South => Direction::South,
+            SideData::East => Direction::East,
+        }
+    }
+}
@@ -1,10 +1,73 @@
+use serde_derive::{Deserialize, Serialize};
+
 use super::vectors::{Vector2f, Vector3f};
+use super::schema::{SpriteData, PickupData, MapError};
+use crate::graphics::TextureAtlas;
+
+/// Distance at which pickups are collected when the map does not give one
+const PICKUP_RADIUS: f32 = 0.5;
+/// Vertical distance up to which pickups are collected, less than a floor
+const PICKUP_HEIGHT: f32 = 0.5;
+/// Distance kept from solid sprites when the map does not give one
+const SOLID_RADIUS: f32 = 0.4;
 
 pub struct Sprite {
     pub pos: Vector3f,
     pub value: u32,
     pub dist: f32,
     pub is_player: bool,
+    /// Index of the enemy drawn by the sprite
+    pub enemy: Option<usize>,
+    /// Item collected by walking over the sprite
+    pub pickup: Option<Pickup>,
+    /// Distance kept by the player and the enemies from a solid sprite, `None` when it can be walked through
+    pub solid: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PickupKind {
+    Health,
+    Armor,
+    Ammo,
+    Weapon,
+    Key,
+    Treasure,
+}
+
+///
+/// Item lying in the map
+///
+#[derive(Clone, PartialEq, Debug)]
+pub struct Pickup {
+    pub kind: PickupKind,
+    /// Health, armor, ammunition or treasure points given
+    pub amount: u32,
+    /// Ammunition type, weapon name or key ID, depending on the kind
+    pub item: Option<String>,
+    /// Distance from the player at which the item is collected
+    pub radius: f32,
+}
+
+impl Pickup {
+    fn from_data(data: &PickupData, path: &str) -> Result<Pickup, MapError> {
+        let radius = data.radius.unwrap_or(PICKUP_RADIUS);
+
+        if radius.is_nan() || radius <= 0.0 {
+            return Err(MapError::new(format!("{}.radius", path), "the radius must be positive"));
+        }
+        let needs_item = matches!(data.kind, PickupKind::Ammo | PickupKind::Weapon | PickupKind::Key);
+
+        if needs_item && data.item.is_none() {
+            return Err(MapError::new(format!("{}.item", path), format!("{:?} pickups need an item", data.kind).to_lowercase()));
+        }
+        Ok(Pickup { kind: data.kind, amount: data.amount, item: data.item.clone(), radius })
+    }
+
+    /// Whether a player at a position collects the item lying at another, on the same floor
+    pub fn reaches(&self, item: Vector3f, player: Vector3f) -> bool {
+        (item.x - player.x).hypot(item.y - player.y) <= self.radius && (item.z - player.z).abs() < PICKUP_HEIGHT
+    }
 }
 
 #[derive(Default, Copy, Clone)]
@@ -17,41 +80,66 @@ pub struct Zorigin {
     pub portal_degree: f32,
 }
 
-#[derive(Default, Clone)]
+#[derive(Default, Copy, Clone)]
 pub struct Zdist {
     pub dist: f32,
     pub portal_depth: usize,
 }
 
 impl Sprite {
-    pub fn get_player_pos_from_json(json: &serde_json::Value) -> Vector3f {
+    pub fn get_player_pos(sprites: &[SpriteData]) -> Vector3f {
         let mut pos = Vector3f::default();
 
-        for sprite in json.as_array().unwrap() {
-            let value = sprite["index"].as_u64().unwrap() as u32;
-            if value != 0 {
+        for sprite in sprites {
+            if sprite.index != 0 {
                 continue;
             }
-            let x = sprite["pos"]["x"].as_f64().unwrap() as f32 + 0.5;
-            let y = sprite["pos"]["y"].as_f64().unwrap() as f32 + 0.5;
-            let z = sprite["pos"]["z"].as_f64().unwrap() as f32;
-
-            pos = Vector3f::new(x, y, z);
+            pos = Vector3f::new(sprite.pos.x + 0.5, sprite.pos.y + 0.5, sprite.pos.z);
         }
         pos
     }
 
-    pub fn parse_sprites_from_json(json: &serde_json::Value) -> Vec<Sprite> {
-        let mut sprites = Vec::new();
-        for sprite in json.as_array().unwrap() {
-            let value = sprite["index"].as_u64().unwrap() as u32;
-            let x = sprite["pos"]["x"].as_f64().unwrap() as f32 + 0.5;
-            let y = sprite["pos"]["y"].as_f64().unwrap() as f32 + 0.5;
-            let z = sprite["pos"]["z"].as_f64().unwrap() as f32;
-            let is_player = value == 0;
+    pub fn parse_sprites(sprites: &[SpriteData], atlas: &TextureAtlas) -> Result<Vec<Sprite>, MapError> {
+        sprites.iter().enumerate().map(|(idx, sprite)| {
+            if sprite.index != 0 && atlas.sprite(sprite.index).is_none() {
+                return Err(MapError::new(format!("sprites[{}].index", idx), format!("no texture is bound to sprite {}", sprite.index)));
+            }
+            let pos = Vector3f::new(sprite.pos.x + 0.5, sprite.pos.y + 0.5, sprite.pos.z);
+            let is_player = sprite.index == 0;
+            let pickup = match &sprite.pickup {
+                Some(pickup) => Some(Pickup::from_data(pickup, &format!("sprites[{}].pickup", idx))?),
+                None => None,
+            };
 
-            sprites.push(Sprite { pos: Vector3f::new(x, y, z), value: value - 1, dist: 0.0, is_player });
-        }
-        sprites
+            let solid = match sprite.radius.unwrap_or(SOLID_RADIUS) {
+                radius if radius.is_nan() || radius <= 0.0 => {
+                    return Err(MapError::new(format!("sprites[{}].radius", idx), "the radius must be positive"));
+                }
+                radius => (sprite.solid && !is_player).then_some(radius),
+            };
+
+            Ok(Sprite { pos, value: sprite.index.saturating_sub(1), dist: 0.0, is_player, enemy: None, pickup, solid })
+        }).collect()
+    }
+
+    /// Whether a solid sprite stands in the way of a move, on the same floor
+    ///
+    /// Moves getting away from a sprite are allowed, so that one starting inside it can leave
+    ///
+    /// # Arguments
+    /// * sprites - Sprites which may block the way
+    /// * from - Position before the move, z being the height of the feet
+    /// * to - Position after the move
+    /// * margin - Half of the width of the one moving
+    pub fn blocks(sprites: &[Sprite], from: Vector3f, to: Vector3f, margin: f32) -> bool {
+        sprites.iter().any(|sprite| match sprite.solid {
+            //Sprites are a floor high
+            Some(radius) if to.z < sprite.pos.z + 1.0 && to.z + 1.0 > sprite.pos.z => {
+                let dist = (to.x - sprite.pos.x).hypot(to.y - sprite.pos.y);
+
+                dist < radius + margin && dist < (from.x - sprite.pos.x).hypot(from.y - sprite.pos.y)
+            }
+            _ => false,
+        })
     }
-}
\ No newline at end of file
+}
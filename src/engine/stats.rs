@@ -0,0 +1,31 @@
+use serde_derive::Serialize;
+
+/// How many of a kind of thing the player got, out of the ones in the level
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Tally {
+    pub found: usize,
+    pub total: usize,
+}
+
+impl Tally {
+    pub fn new(found: usize, total: usize) -> Tally {
+        Tally { found, total }
+    }
+}
+
+///
+/// End of level statistics
+///
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Stats {
+    /// Time spent in the level (Second)
+    pub time: f32,
+    /// Time to beat given by the map (Second)
+    pub par_time: Option<f32>,
+    /// Enemies killed
+    pub kills: Tally,
+    /// Pushwalls found
+    pub secrets: Tally,
+    /// Treasure items collected
+    pub treasure: Tally,
+}
@@ -0,0 +1,54 @@
+use super::schema::{SurfaceData, MapError};
+use crate::graphics::TextureAtlas;
+
+///
+/// Floor and ceiling of a cell
+///
+/// Wall values of the texture atlas, `None` keeps the flat colors
+///
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub struct Surface {
+    /// Seen from above, at the bottom of the cell
+    pub floor: Option<u32>,
+    /// Seen from below, at the top of the cell
+    pub ceiling: Option<u32>,
+}
+
+impl Surface {
+    /// Build the default surface of a floor from its description
+    ///
+    /// # Arguments
+    /// * data - Surface description
+    /// * path - JSON path of the description, used in errors
+    /// * atlas - Textures the values must be bound to
+    ///
+    /// Return the surface or the error
+    pub fn from_data(data: &SurfaceData, path: &str, atlas: &TextureAtlas) -> Result<Surface, MapError> {
+        Surface::default().overridden(data.floor, data.ceiling, path, atlas)
+    }
+
+    /// Replace the textures which are given
+    ///
+    /// # Arguments
+    /// * floor - New floor texture
+    /// * ceiling - New ceiling texture
+    /// * path - JSON path of the description holding them, used in errors
+    /// * atlas - Textures the values must be bound to
+    ///
+    /// Return the new surface or the error
+    pub fn overridden(self, floor: Option<u32>, ceiling: Option<u32>, path: &str, atlas: &TextureAtlas) -> Result<Surface, MapError> {
+        Ok(Surface {
+            floor: Surface::check(floor, path, "floor", atlas)?.or(self.floor),
+            ceiling: Surface::check(ceiling, path, "ceiling", atlas)?.or(self.ceiling),
+        })
+    }
+
+    fn check(texture: Option<u32>, path: &str, field: &str, atlas: &TextureAtlas) -> Result<Option<u32>, MapError> {
+        match texture {
+            Some(texture) if !atlas.has_wall(texture) => {
+                Err(MapError::new(format!("{}.{}", path, field), format!("no texture is bound to {}", texture)))
+            }
+            texture => Ok(texture),
+        }
+    }
+}
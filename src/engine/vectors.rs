@@ -68,6 +68,11 @@ impl Vector2f {
         self.x = self.x * new_rotation.cos() - self.y * new_rotation.sin();
         self.y = old_x * new_rotation.sin() + self.y * new_rotation.cos();
     }
+
+    /// Euclidean length of the vector
+    pub fn length(&self) -> f32 {
+        (self.x * self.x + self.y * self.y).sqrt()
+    }
 }
 
 #[repr(C)]
@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+
+use super::map::Map;
+use super::player::Player;
+use super::rayobject::Ray;
+use super::sprite::Sprite;
+use super::vectors::{Vector2f, Vector3f};
+use super::schema::{WeaponData, MapError};
+
+/// Half of the width of the sprites hit by the shots
+const HIT_RADIUS: f32 = 0.35;
+
+///
+/// Hitscan weapon
+///
+/// Shots go straight from the eyes of the player and hit at once
+///
+#[derive(Clone, Debug)]
+pub struct Weapon {
+    pub name: String,
+    /// Shots per second while the trigger is held
+    pub fire_rate: f32,
+    /// Largest deviation of a shot from the center of the view (Radian)
+    pub spread: f32,
+    /// Distance up to which shots hit
+    pub range: f32,
+    /// Health taken by a shot
+    pub damage: u32,
+    /// Type of ammunition used by a shot, `None` for melee weapons
+    pub ammo: Option<String>,
+}
+
+impl Weapon {
+    fn new(name: &str, fire_rate: f32, spread: f32, range: f32, damage: u32, ammo: Option<&str>) -> Weapon {
+        Weapon {
+            name: name.to_string(),
+            fire_rate,
+            spread: spread.to_radians(),
+            range,
+            damage,
+            ammo: ammo.map(str::to_string),
+        }
+    }
+
+    /// Weapons of the original game, the knife and the pistol being owned from the start
+    ///
+    /// Return the weapons and whether each one is owned
+    pub fn builtin() -> Vec<(Weapon, bool)> {
+        vec![
+            (Weapon::new("knife", 2.5, 0.0, 1.5, 15, None), true),
+            (Weapon::new("pistol", 3.0, 2.0, 30.0, 12, Some("bullets")), true),
+            (Weapon::new("machine_gun", 8.0, 3.0, 30.0, 12, Some("bullets")), false),
+            (Weapon::new("chain_gun", 14.0, 5.0, 30.0, 12, Some("bullets")), false),
+        ]
+    }
+
+    /// Build the weapons of a map from their description
+    ///
+    /// # Arguments
+    /// * weapons - Weapon descriptions
+    ///
+    /// Return the weapons and whether each one is owned, or the first error found
+    pub fn parse_weapons(weapons: &[WeaponData]) -> Result<Vec<(Weapon, bool)>, MapError> {
+        weapons.iter().enumerate().map(|(idx, weapon)| {
+            if weapon.fire_rate.is_nan() || weapon.fire_rate <= 0.0 {
+                return Err(MapError::new(format!("weapons[{}].fire_rate", idx), "the fire rate must be positive"));
+            }
+            if weapon.range.is_nan() || weapon.range <= 0.0 {
+                return Err(MapError::new(format!("weapons[{}].range", idx), "the range must be positive"));
+            }
+            if !(0.0..90.0).contains(&weapon.spread) {
+                return Err(MapError::new(format!("weapons[{}].spread", idx), "the spread must be between 0 and 90 degrees"));
+            }
+            Ok((Weapon::new(&weapon.name, weapon.fire_rate, weapon.spread, weapon.range, weapon.damage, weapon.ammo.as_deref()), weapon.owned))
+        }).collect()
+    }
+}
+
+///
+/// Weapons and ammunition of the player
+///
+#[derive(Clone, Default)]
+pub struct Arsenal {
+    weapons: Vec<Weapon>,
+    owned: Vec<bool>,
+    current: usize,
+    ammo: HashMap<String, u32>,
+    trigger: bool,
+    cooldown: f32,
+    seed: u32,
+}
+
+impl Arsenal {
+    /// Construct an arsenal holding the last weapon owned, the best one
+    ///
+    /// # Arguments
+    /// * weapons - Every weapon of the game and whether it is owned
+    /// * ammo - Ammunition by type
+    pub fn new(weapons: Vec<(Weapon, bool)>, ammo: HashMap<String, u32>) -> Arsenal {
+        let (weapons, owned): (Vec<Weapon>, Vec<bool>) = weapons.into_iter().unzip();
+
+        Arsenal {
+            current: owned.iter().rposition(|&owned| owned).unwrap_or(0),
+            weapons,
+            owned,
+            ammo,
+            trigger: false,
+            cooldown: 0.0,
+            seed: 0x2545_f491,
+        }
+    }
+
+    pub fn weapons(&self) -> &[Weapon] {
+        &self.weapons
+    }
+
+    /// Weapon in hand, `None` when none is owned
+    pub fn current(&self) -> Option<&Weapon> {
+        self.weapons.get(self.current).filter(|_| self.owned[self.current])
+    }
+
+    /// Whether the player has a weapon
+    pub fn owns(&self, weapon: usize) -> bool {
+        self.owned.get(weapon).copied().unwrap_or(false)
+    }
+
+    /// Ammunition left of a type
+    pub fn ammo(&self, ammo: &str) -> u32 {
+        self.ammo.get(ammo).copied().unwrap_or(0)
+    }
+
+    /// Index of a weapon
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.weapons.iter().position(|weapon| weapon.name == name)
+    }
+
+    /// Add ammunition of a type
+    pub fn add_ammo(&mut self, ammo: &str, amount: u32) {
+        *self.ammo.entry(ammo.to_string()).or_insert(0) += amount;
+    }
+
+    /// Give a weapon with its ammunition, taking it in hand when it is better than the current one
+    ///
+    /// # Arguments
+    /// * weapon - Index of the weapon
+    /// * ammo - Ammunition given with it
+    pub fn give(&mut self, weapon: usize, ammo: u32) {
+        if let Some(ammo_type) = self.weapons[weapon].ammo.clone() {
+            self.add_ammo(&ammo_type, ammo);
+        }
+        self.owned[weapon] = true;
+        if weapon > self.current || !self.owns(self.current) {
+            self.current = weapon;
+        }
+    }
+
+    /// Take over the weapons and ammunition of another arsenal, by name
+    ///
+    /// The weapons it holds which this one does not know are lost
+    pub fn carry(&mut self, previous: &Arsenal) {
+        for (weapon, owned) in previous.weapons.iter().zip(&previous.owned) {
+            if let Some(idx) = self.find(&weapon.name) {
+                self.owned[idx] |= *owned;
+            }
+        }
+        self.ammo = previous.ammo.clone();
+        if let Some(idx) = previous.current().and_then(|weapon| self.find(&weapon.name)) {
+            self.current = idx;
+        }
+    }
+
+    /// Take a weapon in hand, if it is owned
+    pub fn select(&mut self, weapon: usize) {
+        if self.owns(weapon) {
+            self.current = weapon;
+        }
+    }
+
+    /// Press or release the trigger
+    pub fn pull(&mut self, pressed: bool) {
+        self.trigger = pressed;
+    }
+
+    /// Fire the weapon in hand while the trigger is held and it is ready
+    ///
+    /// # Arguments
+    /// * delta - Elapsed time (Second)
+    ///
+    /// Return the index of the weapon fired and the deviation of the shot (Radian)
+    pub fn update(&mut self, delta: f32) -> Option<(usize, f32)> {
+        self.cooldown = (self.cooldown - delta).max(0.0);
+        if !self.trigger || self.cooldown > 0.0 {
+            return None;
+        }
+        if !self.owns(self.current) {
+            return None;
+        }
+        let weapon = &self.weapons[self.current];
+        let (fire_rate, spread) = (weapon.fire_rate, weapon.spread);
+
+        if let Some(ammo) = &weapon.ammo {
+            match self.ammo.get_mut(ammo) {
+                Some(count) if *count > 0 => *count -= 1,
+                _ => return None,
+            }
+        }
+        self.cooldown = 1.0 / fire_rate;
+        Some((self.current, spread * (2.0 * self.random() - 1.0)))
+    }
+
+    /// Pseudo-random number in [0, 1[, the same sequence on every run
+    fn random(&mut self) -> f32 {
+        //Xorshift
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        (self.seed >> 8) as f32 / (1 << 24) as f32
+    }
+}
+
+/// What a shot hits
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Target {
+    /// Nothing within range
+    Nothing,
+    /// Cell of the wall
+    Wall(Vector3f),
+    /// Index of the sprite
+    Sprite(usize),
+}
+
+///
+/// Hitscan shot
+///
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Shot {
+    pub target: Target,
+    /// Distance of the hit, along the portals crossed
+    pub dist: f32,
+}
+
+impl Shot {
+    /// Cast a shot from the center of the view, through the portals
+    ///
+    /// # Arguments
+    /// * map - Map holding the walls
+    /// * player - Player shooting
+    /// * angle - Deviation from the center of the view (Radian)
+    /// * range - Distance up to which the shot hits
+    /// * sprites - Sprites which may be hit
+    /// * hittable - Whether a sprite stops the shot
+    ///
+    /// Return the nearest wall or sprite hit
+    pub fn cast<F: Fn(&Sprite) -> bool>(map: &Map, player: &Player, angle: f32, range: f32, sprites: &[Sprite], hittable: F) -> Shot {
+        let mut ray = Ray::new(player, Vector2f::new(angle.tan() / player.plane.length(), 0.0));
+        //Distances along the ray are expressed in the length of its direction
+        let scale = ray.direction().x.hypot(ray.direction().y);
+        let reach = range / scale;
+        let mut start = 0.0;
+
+        loop {
+            let (origin, dir) = (ray.origin, ray.direction());
+            let hit = ray.cast(map);
+            let end = if hit.value.is_some() { hit.dist.min(reach) } else { reach };
+            //Cameras behind the portals are in the wall, the part of the ray before the portal is ignored
+            let sprite = sprites.iter().enumerate()
+                .filter(|(_, sprite)| !sprite.is_player && hittable(sprite) && (sprite.pos.z - (origin.z - 0.5)).abs() < 0.5)
+                .filter_map(|(idx, sprite)| {
+                    let to = Vector2f::new(sprite.pos.x - origin.x, sprite.pos.y - origin.y);
+                    let along = (to.x * dir.x + to.y * dir.y) / (scale * scale);
+                    let across = (to.x * dir.y - to.y * dir.x).abs() / scale;
+
+                    (along >= start && along <= end && across <= HIT_RADIUS).then_some((idx, along))
+                })
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+            if let Some((idx, along)) = sprite {
+                return Shot { target: Target::Sprite(idx), dist: along * scale };
+            }
+            if hit.value.is_none() || hit.dist > reach {
+                return Shot { target: Target::Nothing, dist: range };
+            }
+            match map.portals_at(hit.pos, hit.dir) {
+                Some((Some(source), Some(dest))) if ray.pass_through_portal(dest, source) => start = hit.dist,
+                _ => return Shot { target: Target::Wall(hit.pos), dist: hit.dist * scale },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deviations of a thousand shots of a weapon spreading 5 degrees
+    fn shots() -> Vec<f32> {
+        let mut arsenal = Arsenal::new(vec![(Weapon::new("machine_gun", 10.0, 5.0, 30.0, 12, None), true)], HashMap::new());
+
+        arsenal.pull(true);
+        (0..1000).filter_map(|_| arsenal.update(0.1)).map(|(_, angle)| angle).collect()
+    }
+
+    #[test]
+    fn spread_within_cone() {
+        let cone = 5f32.to_radians();
+        let angles = shots();
+
+        assert_eq!(angles.len(), 1000);
+        assert!(angles.iter().all(|angle| angle.abs() <= cone));
+        //Both sides of the cone are reached
+        assert!(angles.iter().any(|&angle| angle < -cone * 0.9) && angles.iter().any(|&angle| angle > cone * 0.9));
+        //The same seed gives the same shots
+        assert_eq!(angles, shots());
+    }
+}
@@ -0,0 +1,506 @@
+use std::collections::BTreeSet;
+
+use crate::graphics::{Canvas, RenderTarget, MemoryTarget, TextureAtlas};
+use crate::graphics::screenshot::ScreenshotError;
+use crate::engine::{Player, Status, Stats, Tally, Map, Enemy, Event, Weapon, Arsenal, Shot, Target, Action, Key, Bindings, MouseSettings, Axis, GamepadSettings, MAX_PITCH};
+use crate::engine::vectors::{Vector2f, Vector3f};
+use crate::engine::sprite::{Sprite, Pickup, PickupKind};
+use crate::engine::schema::{MapData, CampaignData, MapError};
+use crate::renderer::Renderer;
+
+pub struct Game {
+    map: Map,
+    player: Player,
+    sprites: Vec<Sprite>,
+    enemies: Vec<Enemy>,
+    events: Vec<Event>,
+    atlas: TextureAtlas,
+    /// Keys of the host triggering the actions of the player
+    bindings: Bindings,
+    mouse: MouseSettings,
+    /// Mouse rotation not applied yet, while smoothing (Radian)
+    look: Vector2f,
+    gamepad: GamepadSettings,
+    /// Last value of each gamepad axis, by [`Axis`]
+    axes: [f32; 4],
+    /// Gamepad buttons held down
+    buttons: BTreeSet<u32>,
+    /// Map descriptions of the campaign, the one played included
+    maps: Vec<serde_json::Value>,
+    /// Index of the map played
+    level: usize,
+    /// Whether the player reached an exit, which freezes the level
+    finished: bool,
+    /// Time spent in the level (Second)
+    time: f32,
+    par_time: Option<f32>,
+    /// Number of treasure items in the level when it starts
+    treasures: usize,
+
+    canvas: Canvas,
+    renderer: Renderer,
+}
+
+impl Game {
+    /// Construct a game rendering into any target
+    ///
+    /// The textures are the ones compiled into the crate, see [`TextureAtlas::builtin`]
+    ///
+    /// # Arguments
+    /// * map - Map description
+    /// * width - Canvas width
+    /// * height - Canvas height
+    /// * target - Where the frames are presented
+    ///
+    /// Return Game object, or the first error found in the map
+    pub fn with_target(map: &serde_json::Value, width: usize, height: usize, target: Box<dyn RenderTarget>) -> Result<Game, MapError> {
+        Game::with_atlas(map, default_atlas(), width, height, target)
+    }
+
+    /// Construct a game with its own textures
+    ///
+    /// # Arguments
+    /// * map - Map description, its values must be bound in the atlas
+    /// * atlas - Textures of the walls, floors, ceilings and sprites
+    /// * width - Canvas width
+    /// * height - Canvas height
+    /// * target - Where the frames are presented
+    ///
+    /// Return Game object, or the first error found in the map
+    pub fn with_atlas(map: &serde_json::Value, atlas: TextureAtlas, width: usize, height: usize, target: Box<dyn RenderTarget>) -> Result<Game, MapError> {
+        let level = Level::load(map, &atlas)?;
+
+        Ok(Game::new(level, vec![map.clone()], atlas, width, height, target))
+    }
+
+    /// Construct a game playing the maps of a campaign in order, see [`next_level`](Game::next_level)
+    ///
+    /// # Arguments
+    /// * campaign - Campaign description, e.g. `{"maps": [map1, map2]}`
+    /// * atlas - Textures of the walls, floors, ceilings and sprites of every map
+    /// * width - Canvas width
+    /// * height - Canvas height
+    /// * target - Where the frames are presented
+    ///
+    /// Return Game object on the first map, or the first error found in the maps, e.g. `maps[1].cells[0][3].value: ...`
+    pub fn with_campaign(campaign: &serde_json::Value, atlas: TextureAtlas, width: usize, height: usize, target: Box<dyn RenderTarget>) -> Result<Game, MapError> {
+        let data = CampaignData::from_json(campaign)?;
+        //Every map is checked before the episode starts
+        for (idx, map) in data.maps.iter().enumerate().skip(1) {
+            Level::load(map, &atlas).map_err(|err| in_campaign(idx, err))?;
+        }
+        let level = Level::load(&data.maps[0], &atlas).map_err(|err| in_campaign(0, err))?;
+
+        Ok(Game::new(level, data.maps, atlas, width, height, target))
+    }
+
+    fn new(level: Level, maps: Vec<serde_json::Value>, atlas: TextureAtlas, width: usize, height: usize, target: Box<dyn RenderTarget>) -> Game {
+        Game {
+            map: level.map,
+            player: level.player,
+            sprites: level.sprites,
+            enemies: level.enemies,
+            events: Vec::new(),
+            atlas,
+            bindings: Bindings::default(),
+            mouse: MouseSettings::default(),
+            look: Vector2f::default(),
+            gamepad: GamepadSettings::default(),
+            axes: [0.0; 4],
+            buttons: BTreeSet::new(),
+            maps,
+            level: 0,
+            finished: false,
+            time: 0.0,
+            par_time: level.par_time,
+            treasures: level.treasures,
+            canvas: Canvas::new(width, height, target),
+            renderer: Renderer::new(width, height),
+        }
+    }
+
+    /// Construct a game rendering in memory, without any browser
+    ///
+    /// # Arguments
+    /// * map - Map description
+    /// * width - Canvas width
+    /// * height - Canvas height
+    ///
+    /// Return Game object, or the first error found in the map
+    pub fn headless(map: &serde_json::Value, width: usize, height: usize) -> Result<Game, MapError> {
+        Game::with_target(map, width, height, Box::new(MemoryTarget))
+    }
+
+    /// Move the camera
+    ///
+    /// # Arguments
+    /// * pos - New position, z being the floor level
+    /// * angle - Yaw (Radian), 0 looks toward +x
+    pub fn set_camera(&mut self, pos: Vector3f, angle: f32) {
+        self.player.set_pose(pos, angle);
+    }
+
+    /// Vertical angle of the view, positive looking up (Radian)
+    pub fn pitch(&self) -> f32 {
+        self.player.pitch
+    }
+
+    /// Look up or down, within [`MAX_PITCH`] either way
+    ///
+    /// # Arguments
+    /// * angle - Pitch (Radian), 0 looks at the horizon
+    pub fn set_pitch(&mut self, angle: f32) {
+        self.player.pitch = angle.clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    /// Press or release a key given by its numeric code, e.g. `KeyboardEvent.which`
+    pub fn process_event(&mut self, key: u32, pressed: bool) {
+        self.process_key(&Key::Which(key), pressed);
+    }
+
+    /// Press or release a key, ignored when it is not bound
+    pub fn process_key(&mut self, key: &Key, pressed: bool) {
+        if let Some(action) = self.bindings.action(key) {
+            self.process_action(action, pressed);
+        }
+    }
+
+    /// Start or stop an action, whatever the key
+    pub fn process_action(&mut self, action: Action, pressed: bool) {
+        self.player.handle_action(action, pressed);
+    }
+
+    /// Turn the view and look up or down with the mouse, see [`MouseSettings`]
+    ///
+    /// # Arguments
+    /// * dx - Horizontal move, e.g. `MouseEvent.movementX` (Pixel)
+    /// * dy - Vertical move, e.g. `MouseEvent.movementY` (Pixel), moving down looks down
+    pub fn process_mouse_move(&mut self, dx: f32, dy: f32) {
+        let rotation = self.mouse.rotation(dx, dy);
+
+        self.look = Vector2f::new(self.look.x + rotation.x, self.look.y + rotation.y);
+    }
+
+    /// Move an analog axis of a gamepad, e.g. every frame with `Gamepad.axes`
+    ///
+    /// # Arguments
+    /// * axis - Stick axis
+    /// * value - Deflection, from -1 to 1, see [`GamepadSettings`] for the dead zone and the response curve
+    pub fn process_axis(&mut self, axis: Axis, value: f32) {
+        self.axes[axis as usize] = if value.is_nan() { 0.0 } else { value.clamp(-1.0, 1.0) };
+        self.steer();
+    }
+
+    /// Press or release a gamepad button, e.g. every frame with `Gamepad.buttons`
+    ///
+    /// Buttons work like keys, see [`Key::Button`]: holding one down triggers its action once
+    ///
+    /// # Arguments
+    /// * button - Index of the button
+    /// * pressed - Whether the button is down
+    pub fn process_button(&mut self, button: u32, pressed: bool) {
+        let changed = if pressed { self.buttons.insert(button) } else { self.buttons.remove(&button) };
+
+        if changed {
+            self.process_key(&Key::Button { button }, pressed);
+        }
+    }
+
+    /// Dead zone, response curve and inverted axis of the gamepad sticks
+    pub fn gamepad(&self) -> &GamepadSettings {
+        &self.gamepad
+    }
+
+    pub fn set_gamepad(&mut self, gamepad: GamepadSettings) {
+        self.gamepad = gamepad;
+        self.steer();
+    }
+
+    /// Hand the deflections of the sticks over to the player
+    fn steer(&mut self) {
+        let axis = |axis: Axis| self.axes[axis as usize];
+        let walk = self.gamepad.stick(axis(Axis::MoveX), axis(Axis::MoveY));
+        let look = self.gamepad.stick(axis(Axis::LookX), axis(Axis::LookY));
+        let up = if self.gamepad.invert_y { look.y } else { -look.y };
+
+        //Pushing the walking stick up walks forward
+        self.player.steer(Vector2f::new(-walk.y, walk.x), Vector2f::new(look.x, up));
+    }
+
+    /// Sensitivity, smoothing and inverted axes of the mouse
+    pub fn mouse(&self) -> &MouseSettings {
+        &self.mouse
+    }
+
+    pub fn set_mouse(&mut self, mouse: MouseSettings) {
+        self.mouse = mouse;
+    }
+
+    /// Keys bound to the actions, see [`Bindings::default`]
+    pub fn bindings(&self) -> &Bindings {
+        &self.bindings
+    }
+
+    /// Bind other keys to the actions
+    pub fn set_bindings(&mut self, bindings: Bindings) {
+        self.bindings = bindings;
+    }
+
+    /// Run the level for some time, frozen once finished, and draw it
+    ///
+    /// # Arguments
+    /// * delta - Elapsed time (Second)
+    pub fn update(&mut self, delta: f32) {
+        self.events.clear();
+        if !self.finished {
+            self.simulate(delta);
+            self.finished = self.events.contains(&Event::Exited);
+        }
+
+        self.renderer.draw_view(&mut self.canvas, &self.map, &self.atlas, &self.player);
+        self.renderer.draw_sprites(&mut self.canvas, &self.map, &self.atlas, &mut self.sprites, &self.enemies, &self.player);
+        self.canvas.update();
+    }
+
+    /// Move the player, the enemies and the walls
+    fn simulate(&mut self, delta: f32) {
+        let look = self.mouse.smoothed(self.look, delta);
+
+        self.look = Vector2f::new(self.look.x - look.x, self.look.y - look.y);
+        self.player.turn(look.x);
+        self.player.tilt(-look.y);
+        self.time += delta;
+        let occupants = self.occupants();
+
+        self.map.update(delta, &occupants);
+        self.events.extend(self.player.update(&mut self.map, &self.sprites, &occupants, delta));
+        self.collect();
+        if self.player.is_alive() {
+            if let Some((weapon, angle)) = self.player.weapons.update(delta) {
+                self.fire(weapon, angle);
+            }
+        }
+        for enemy in &mut self.enemies {
+            let event = enemy.update(&mut self.map, &self.player, &self.sprites, delta);
+            let damage = match &event {
+                Some(Event::Fired { damage, .. }) => *damage,
+                _ => 0,
+            };
+
+            self.events.extend(event);
+            self.events.extend(self.player.hurt(damage));
+        }
+        for sprite in &mut self.sprites {
+            if let Some(idx) = sprite.enemy {
+                sprite.pos = self.enemies[idx].pos;
+            }
+        }
+    }
+
+    /// Positions of the player, the standing enemies and the solid sprites, no pushwall slides over them
+    fn occupants(&self) -> Vec<Vector3f> {
+        let enemies = self.enemies.iter().filter(|enemy| !enemy.is_down()).map(|enemy| enemy.pos);
+        let sprites = self.sprites.iter().filter(|sprite| sprite.solid.is_some()).map(|sprite| sprite.pos);
+
+        std::iter::once(self.player.pos).chain(enemies).chain(sprites).collect()
+    }
+
+    /// Give the player the items it walks over, removing their sprites
+    fn collect(&mut self) {
+        let player = &mut self.player;
+        let events = &mut self.events;
+
+        self.sprites.retain(|sprite| match &sprite.pickup {
+            Some(pickup) if pickup.reaches(sprite.pos, player.pos) && player.collect(pickup) => {
+                events.push(Event::Picked { kind: pickup.kind, amount: pickup.amount, item: pickup.item.clone() });
+                false
+            }
+            _ => true,
+        });
+    }
+
+    /// Shoot with a weapon of the player, hurting the enemy hit
+    fn fire(&mut self, weapon: usize, angle: f32) {
+        let (range, damage) = {
+            let weapon = &self.player.weapons.weapons()[weapon];
+
+            (weapon.range, weapon.damage)
+        };
+        let enemies = &self.enemies;
+        //Solid sprites stop the shots without being hurt, like the walls
+        let shot = Shot::cast(&self.map, &self.player, angle, range, &self.sprites, |sprite| {
+            sprite.solid.is_some() || sprite.enemy.is_some_and(|idx| !enemies[idx].is_down())
+        });
+
+        self.events.push(Event::Shot { weapon });
+        if let Target::Sprite(sprite) = shot.target {
+            if let Some(enemy) = self.sprites[sprite].enemy {
+                let event = self.enemies[enemy].hurt(damage).unwrap_or(Event::Hit { enemy, damage });
+
+                self.events.push(event);
+            }
+        }
+    }
+
+    /// Bring the player back to life at the last checkpoint, or where the map starts
+    pub fn respawn(&mut self) {
+        self.player.respawn();
+    }
+
+    /// Load the next map of the campaign, the player keeping its health, armor, treasure, weapons and ammunition
+    ///
+    /// May be called before the level is finished, to skip it, a dead player coming back with full health
+    ///
+    /// Return whether there was a next map, `false` once the campaign is over
+    pub fn next_level(&mut self) -> Result<bool, MapError> {
+        let map = match self.maps.get(self.level + 1) {
+            Some(map) => map,
+            None => return Ok(false),
+        };
+        let mut level = Level::load(map, &self.atlas).map_err(|err| in_campaign(self.level + 1, err))?;
+
+        level.player.carry(&self.player);
+        self.map = level.map;
+        self.player = level.player;
+        self.sprites = level.sprites;
+        self.enemies = level.enemies;
+        self.events.clear();
+        self.look = Vector2f::default();
+        self.steer();
+        self.level += 1;
+        self.finished = false;
+        self.time = 0.0;
+        self.par_time = level.par_time;
+        self.treasures = level.treasures;
+        Ok(true)
+    }
+
+    /// Index of the map played in the campaign
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    /// Whether the player reached an exit of the level
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Time, kills, secrets and treasure of the level, once the player reached an exit
+    pub fn stats(&self) -> Option<Stats> {
+        if !self.finished {
+            return None;
+        }
+        let treasures = treasures(&self.sprites);
+        let (secrets, pushwalls) = self.map.secrets();
+
+        Some(Stats {
+            time: self.time,
+            par_time: self.par_time,
+            kills: Tally::new(self.enemies.iter().filter(|enemy| enemy.is_down()).count(), self.enemies.len()),
+            secrets: Tally::new(secrets, pushwalls),
+            treasure: Tally::new(self.treasures - treasures, self.treasures),
+        })
+    }
+
+    /// Health, armor and weapon of the player
+    pub fn status(&self) -> Status {
+        self.player.status()
+    }
+
+    /// Weapons and ammunition of the player
+    pub fn weapons(&self) -> &Arsenal {
+        &self.player.weapons
+    }
+
+    /// Enemies of the map, the dead ones included
+    pub fn enemies(&self) -> &[Enemy] {
+        &self.enemies
+    }
+
+    /// Events of the last [`update`](Game::update), oldest first
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// RGBA pixels of the last frame drawn by [`update`](Game::update), row by row
+    pub fn frame(&self) -> &[u8] {
+        self.canvas.buffer()
+    }
+
+    /// Encode the last frame drawn by [`update`](Game::update) as a PNG image
+    ///
+    /// # Arguments
+    /// * scale - Size multiplier, 1.0 keeps the canvas size, up to [`MAX_SCALE`](crate::graphics::screenshot::MAX_SCALE)
+    ///
+    /// Return the bytes of the PNG file
+    pub fn screenshot(&self, scale: f32) -> Result<Vec<u8>, ScreenshotError> {
+        self.canvas.to_png(scale)
+    }
+}
+
+///
+/// Map being played, with what lies in it
+///
+struct Level {
+    map: Map,
+    player: Player,
+    sprites: Vec<Sprite>,
+    enemies: Vec<Enemy>,
+    par_time: Option<f32>,
+    /// Number of treasure items lying in the map
+    treasures: usize,
+}
+
+impl Level {
+    /// Build a level from its description
+    ///
+    /// # Arguments
+    /// * map - Map description, its values must be bound in the atlas
+    /// * atlas - Textures of the walls, floors, ceilings and sprites
+    ///
+    /// Return the level, or the first error found in the map
+    fn load(map: &serde_json::Value, atlas: &TextureAtlas) -> Result<Level, MapError> {
+        let data = MapData::from_json(map)?;
+        let map = Map::new(&data, atlas)?;
+        let enemies = Enemy::parse_enemies(&data.enemies, &map)?;
+        let mut sprites = Sprite::parse_sprites(&data.sprites, atlas)?;
+        let weapons = match &data.weapons {
+            Some(weapons) => Weapon::parse_weapons(weapons)?,
+            None => Weapon::builtin(),
+        };
+        let mut player = Player::new(Sprite::get_player_pos(&data.sprites));
+        let treasures = treasures(&sprites);
+
+        if data.par_time.is_some_and(|time| time.is_nan() || time < 0.0) {
+            return Err(MapError::new("par_time", "the par time must not be negative"));
+        }
+        player.weapons = Arsenal::new(weapons, data.ammo);
+        for (idx, sprite) in sprites.iter().enumerate() {
+            if let Some(Pickup { kind: PickupKind::Weapon, item: Some(item), .. }) = &sprite.pickup {
+                if player.weapons.find(item).is_none() {
+                    return Err(MapError::new(format!("sprites[{}].pickup.item", idx), format!("unknown weapon \"{}\"", item)));
+                }
+            }
+        }
+        sprites.extend(enemies.iter().enumerate().map(|(idx, enemy)| Sprite { pos: enemy.pos, value: 0, dist: 0.0, is_player: false, enemy: Some(idx), pickup: None, solid: None }));
+        Ok(Level { map, player, sprites, enemies, par_time: data.par_time, treasures })
+    }
+}
+
+/// Number of treasure items lying in a map
+fn treasures(sprites: &[Sprite]) -> usize {
+    sprites.iter().filter(|sprite| sprite.pickup.as_ref().is_some_and(|pickup| pickup.kind == PickupKind::Treasure)).count()
+}
+
+/// Prefix the path of an error with the map of the campaign holding it
+fn in_campaign(idx: usize, err: MapError) -> MapError {
+    MapError::new(format!("maps[{}].{}", idx, err.path), err.message)
+}
+
+/// Textures of the original game when they are compiled in, none otherwise
+fn default_atlas() -> TextureAtlas {
+    #[cfg(feature = "builtin-textures")]
+    return TextureAtlas::builtin();
+    #[cfg(not(feature = "builtin-textures"))]
+    return TextureAtlas::new();
+}
@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::graphics::RGBColor;
+
+///
+/// Texture error
+///
+#[derive(Debug)]
+pub enum TextureError {
+    /// The pixels do not match the given size
+    Size { width: u32, height: u32, len: usize },
+    /// The PNG decoder failed
+    Decoding(png::DecodingError),
+    /// The PNG image has a color type which is not handled
+    Format(png::ColorType),
+    /// No texture has this name
+    Unknown(String),
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextureError::Size { width, height, len } => {
+                write!(f, "a {}x{} texture needs {} RGBA bytes, got {}", width, height, *width as usize * *height as usize * 4, len)
+            }
+            TextureError::Decoding(err) => write!(f, "PNG decoding failed: {}", err),
+            TextureError::Format(color) => write!(f, "unsupported PNG color type {:?}", color),
+            TextureError::Unknown(name) => write!(f, "unknown texture \"{}\"", name),
+        }
+    }
+}
+
+impl std::error::Error for TextureError {}
+
+impl From<png::DecodingError> for TextureError {
+    fn from(err: png::DecodingError) -> TextureError {
+        TextureError::Decoding(err)
+    }
+}
+
+///
+/// Texture
+///
+/// RGBA pixels of any size, row by row
+///
+#[derive(Clone)]
+pub struct Texture {
+    width: u32,
+    height: u32,
+    pixels: Vec<RGBColor>,
+}
+
+impl Texture {
+    /// Construct a texture from RGBA bytes
+    ///
+    /// # Arguments
+    /// * width - Width in pixels
+    /// * height - Height in pixels
+    /// * rgba - RGBA bytes, row by row
+    ///
+    /// Return the texture, or an error when the size does not match the bytes
+    pub fn from_rgba(width: u32, height: u32, rgba: &[u8]) -> Result<Texture, TextureError> {
+        if width == 0 || height == 0 || rgba.len() != width as usize * height as usize * 4 {
+            return Err(TextureError::Size { width, height, len: rgba.len() });
+        }
+        Ok(Texture {
+            width,
+            height,
+            pixels: rgba.chunks_exact(4).map(|p| RGBColor { r: p[0], g: p[1], b: p[2], a: p[3] }).collect(),
+        })
+    }
+
+    /// Decode a texture from a PNG file
+    ///
+    /// # Arguments
+    /// * bytes - Content of the file
+    ///
+    /// Return the texture or the decoding error
+    pub fn from_png(bytes: &[u8]) -> Result<Texture, TextureError> {
+        let mut decoder = png::Decoder::new(bytes);
+
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer)?;
+        let data = &buffer[..info.buffer_size()];
+        let rgba: Vec<u8> = match info.color_type {
+            png::ColorType::Rgba => data.to_vec(),
+            png::ColorType::Rgb => data.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+            png::ColorType::GrayscaleAlpha => data.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+            png::ColorType::Grayscale => data.iter().flat_map(|&p| [p, p, p, 255]).collect(),
+            color => return Err(TextureError::Format(color)),
+        };
+
+        Texture::from_rgba(info.width, info.height, &rgba)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Pixel at integer coordinates, wrapped around the edges
+    pub fn pixel(&self, x: u32, y: u32) -> RGBColor {
+        self.pixels[((y % self.height) * self.width + x % self.width) as usize]
+    }
+
+    /// Pixel at texture coordinates in [0, 1[, both axes being mirrored like the walls of the original game
+    pub fn texel(&self, u: f32, v: f32) -> RGBColor {
+        let x = (self.width - 1).saturating_sub((u * self.width as f32) as u32);
+        let mut y = self.height as i32 - 1 - (v * self.height as f32) as i32;
+
+        //Only rounding errors leave the texture, avoid the division otherwise
+        if y as u32 >= self.height {
+            y = y.rem_euclid(self.height as i32);
+        }
+        self.pixels[(y as u32 * self.width + x) as usize]
+    }
+
+    /// Copy a rectangle of the texture
+    ///
+    /// # Arguments
+    /// * x - Left of the rectangle
+    /// * y - Top of the rectangle
+    /// * width - Width of the rectangle
+    /// * height - Height of the rectangle
+    ///
+    /// Return the new texture, wrapped around the edges
+    pub fn region(&self, x: u32, y: u32, width: u32, height: u32) -> Texture {
+        Texture {
+            width,
+            height,
+            pixels: (0..height).flat_map(|dy| (0..width).map(move |dx| (dx, dy))).map(|(dx, dy)| self.pixel(x + dx, y + dy)).collect(),
+        }
+    }
+
+    /// Copy of the texture with the colors scaled by a factor, used for the walls in the shadow
+    pub fn darkened(&self, factor: f32) -> Texture {
+        let scale = |channel: u8| (channel as f32 * factor).round().clamp(0.0, 255.0) as u8;
+
+        Texture {
+            width: self.width,
+            height: self.height,
+            pixels: self.pixels.iter().map(|p| RGBColor { r: scale(p.r), g: scale(p.g), b: scale(p.b), a: p.a }).collect(),
+        }
+    }
+}
+
+/// Brightness of the generated shadow side of a wall
+const SHADOW: f32 = 0.5;
+
+///
+/// Texture atlas
+///
+/// Named textures filled at runtime, from RGBA buffers or PNG files,
+/// and the bindings which map the values of the map to them:
+/// * walls - cell value to a lit texture and a shadow one, also used by floors and ceilings
+/// * sprites - sprite index (the first one being 1) to a texture
+/// * `"portal"` - portal overlay: white keeps the wall, translucent pixels open on the other side, others are tinted
+/// * `"soldier"` - player and enemy sheet, square cells: 8 columns of views from the front turning
+///   clockwise, rows 0 standing and 1 to 4 walking, then optional rows for the enemies showing the
+///   first column only: 5 aiming, 6 firing, 7 in pain and 8 to 11 dying, the last being the corpse;
+///   enemies stand in the states without a row
+///
+#[derive(Clone, Default)]
+pub struct TextureAtlas {
+    textures: Vec<Texture>,
+    names: HashMap<String, usize>,
+    /// Lit and shadow textures, by cell value
+    walls: HashMap<u32, (usize, usize)>,
+    /// Darker copies made for the cell values bound without a shadow texture, reused by the next ones
+    shadows: HashMap<u32, usize>,
+    sprites: HashMap<u32, usize>,
+}
+
+impl TextureAtlas {
+    /// Construct an empty atlas
+    pub fn new() -> TextureAtlas {
+        TextureAtlas::default()
+    }
+
+    /// Construct the atlas of the textures compiled into the crate
+    ///
+    /// Walls are named `wall0` to `wall15` (`wall0_dark` to `wall15_dark` in the shadow)
+    /// and bound to the cell values 0 to 15, sprites are named `sprite1` to `sprite12`
+    /// and bound to the sprite indexes 1 to 12. The `soldier` sheet only has the standing
+    /// and walking rows
+    #[cfg(feature = "builtin-textures")]
+    pub fn builtin() -> TextureAtlas {
+        use crate::graphics::textures::*;
+
+        let mut atlas = TextureAtlas::new();
+        let walls = Texture::from_rgba(128, WALL_COUNT * 64, &WALL_TEXTURE[..]).unwrap();
+        let sprites = Texture::from_rgba(64, SPRITE_COUNT * 64, &SPRITES_TEXTURE[..]).unwrap();
+        let portal = Texture::from_rgba(64, 128, &PORTAL_TEXTURE[..]).unwrap();
+        let (colors, mask) = (portal.region(0, 64, 64, 64), portal.region(0, 0, 64, 64));
+
+        for value in 0..WALL_COUNT {
+            let light = atlas.add(&format!("wall{}", value), walls.region(0, value * 64, 64, 64));
+            let dark = atlas.add(&format!("wall{}_dark", value), walls.region(64, value * 64, 64, 64));
+
+            atlas.set_wall(value, light, dark);
+        }
+        for index in 1..=SPRITE_COUNT {
+            let texture = atlas.add(&format!("sprite{}", index), sprites.region(0, (index - 1) * 64, 64, 64));
+
+            atlas.sprites.insert(index, texture);
+        }
+        atlas.add("portal", Texture {
+            pixels: colors.pixels.iter().zip(&mask.pixels).map(|(color, mask)| RGBColor { a: mask.a, ..*color }).collect(),
+            ..colors
+        });
+        atlas.add("soldier", Texture::from_rgba(512, SOLDIER_TEXTURE.len() as u32 / 4 / 512, &SOLDIER_TEXTURE[..]).unwrap());
+        atlas
+    }
+
+    /// Add a texture, replacing the one with the same name
+    ///
+    /// # Arguments
+    /// * name - Name the bindings refer to
+    /// * texture - Texture to add
+    ///
+    /// Return the identifier of the texture
+    pub fn add(&mut self, name: &str, texture: Texture) -> usize {
+        match self.names.get(name) {
+            Some(&id) => {
+                self.textures[id] = texture;
+                id
+            }
+            None => {
+                self.textures.push(texture);
+                self.names.insert(name.to_string(), self.textures.len() - 1);
+                self.textures.len() - 1
+            }
+        }
+    }
+
+    /// Add a texture from RGBA bytes, see [`Texture::from_rgba`]
+    pub fn add_rgba(&mut self, name: &str, width: u32, height: u32, rgba: &[u8]) -> Result<usize, TextureError> {
+        Ok(self.add(name, Texture::from_rgba(width, height, rgba)?))
+    }
+
+    /// Add a texture from a PNG file, see [`Texture::from_png`]
+    pub fn add_png(&mut self, name: &str, bytes: &[u8]) -> Result<usize, TextureError> {
+        Ok(self.add(name, Texture::from_png(bytes)?))
+    }
+
+    /// Identifier of a named texture
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.names.get(name).copied()
+    }
+
+    /// Texture with a given name
+    pub fn get(&self, name: &str) -> Option<&Texture> {
+        self.id(name).map(|id| &self.textures[id])
+    }
+
+    fn require(&self, name: &str) -> Result<usize, TextureError> {
+        self.id(name).ok_or_else(|| TextureError::Unknown(name.to_string()))
+    }
+
+    /// Map a cell value to textures
+    ///
+    /// # Arguments
+    /// * value - Value of the cells, floors and ceilings
+    /// * light - Texture of the lit sides
+    /// * dark - Texture of the sides in the shadow, a darker copy of the lit one when `None`
+    ///
+    /// Rebinding a value replaces the darker copy made for it, or drops its pixels
+    /// when a shadow texture is given, the atlas keeping one copy per value at most
+    pub fn bind_wall(&mut self, value: u32, light: &str, dark: Option<&str>) -> Result<(), TextureError> {
+        let light = self.require(light)?;
+        let dark = match dark {
+            Some(dark) => {
+                let dark = self.require(dark)?;
+
+                if let Some(&id) = self.shadows.get(&value) {
+                    self.textures[id] = Texture { width: 0, height: 0, pixels: Vec::new() };
+                }
+                dark
+            }
+            None => {
+                let texture = self.textures[light].darkened(SHADOW);
+
+                match self.shadows.get(&value) {
+                    Some(&id) => {
+                        self.textures[id] = texture;
+                        id
+                    }
+                    None => {
+                        self.textures.push(texture);
+                        self.shadows.insert(value, self.textures.len() - 1);
+                        self.textures.len() - 1
+                    }
+                }
+            }
+        };
+
+        self.set_wall(value, light, dark);
+        Ok(())
+    }
+
+    fn set_wall(&mut self, value: u32, light: usize, dark: usize) {
+        self.walls.insert(value, (light, dark));
+    }
+
+    /// Map a sprite index to a texture
+    ///
+    /// # Arguments
+    /// * index - Index of the sprites, the first one being 1
+    /// * name - Texture of the sprites
+    pub fn bind_sprite(&mut self, index: u32, name: &str) -> Result<(), TextureError> {
+        let texture = self.require(name)?;
+
+        self.sprites.insert(index, texture);
+        Ok(())
+    }
+
+    /// Texture bound to a cell value, in the light or in the shadow
+    pub fn wall(&self, value: u32, lit: bool) -> Option<&Texture> {
+        self.walls.get(&value).map(|&(light, dark)| &self.textures[if lit { light } else { dark }])
+    }
+
+    /// Whether a texture is bound to a cell value
+    pub fn has_wall(&self, value: u32) -> bool {
+        self.walls.contains_key(&value)
+    }
+
+    /// Texture bound to a sprite index
+    pub fn sprite(&self, index: u32) -> Option<&Texture> {
+        self.sprites.get(&index).map(|&id| &self.textures[id])
+    }
+}
@@ -1,10 +1,11 @@
 use super::color::RGBColor;
-use wasm_bindgen::{JsCast, Clamped};
+use super::target::RenderTarget;
+use super::screenshot::{self, ScreenshotError};
 
 ///
 /// Canvas manipulation
 ///
-/// Provides an abstraction for the HTML5 Canvas
+/// Provides a pixel buffer presented to a [`RenderTarget`]
 ///
 pub struct Canvas {
     /// The width of the Canvas
@@ -13,7 +14,7 @@ pub struct Canvas {
     pub height: usize,
 
     buffer: Vec<u8>,
-    context: web_sys::CanvasRenderingContext2d,
+    target: Box<dyn RenderTarget>,
 }
 
 impl Canvas {
@@ -21,28 +22,18 @@ impl Canvas {
     ///
     /// This function creates the canvas with the size and pixels
     ///
-    /// The canvas must exist in the html document
-    /// If it doesn't, it will result in a RuntimeError: "unreachable executed"
-    ///
     /// # Arguments
     /// * width - Canvas width
     /// * height - Canvas height
+    /// * target - Where the frames are presented
     ///
     /// Return Canvas object
-    pub fn new(width: usize, height: usize) -> Canvas {
-        let window = web_sys::window().expect("no global `window` exists");
-        let document = window.document().expect("should have a document on window");
-        let canvas = document.get_element_by_id("canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
-        let context = canvas.get_context("2d").unwrap().unwrap().dyn_into::<web_sys::CanvasRenderingContext2d>().unwrap();
-
-        canvas.set_width(width as u32);
-        canvas.set_height(height as u32);
-
+    pub fn new(width: usize, height: usize, target: Box<dyn RenderTarget>) -> Canvas {
         Canvas {
             width,
             height,
             buffer: vec![0; width * height * 4],
-            context,
+            target,
         }
     }
 
@@ -61,9 +52,37 @@ impl Canvas {
         self.buffer[offset + 3] = 255;
     }
 
-    /// Update the whole canvas
+    /// Put consecutive pixels of a row in the canvas's buffer
+    ///
+    /// # Arguments
+    /// * x - First pixel x position
+    /// * y - Row
+    /// * colors - Colors of the pixels, from left to right
+    pub fn put_row(&mut self, x: usize, y: usize, colors: &[RGBColor]) {
+        let offset = (y * self.width + x) * 4;
+
+        for (pixel, color) in self.buffer[offset..offset + colors.len() * 4].chunks_exact_mut(4).zip(colors) {
+            pixel.copy_from_slice(&[color.r, color.g, color.b, 255]);
+        }
+    }
+
+    /// RGBA pixels of the canvas, row by row
+    pub fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Encode the canvas as a PNG image
+    ///
+    /// # Arguments
+    /// * scale - Size multiplier, 1.0 keeps the canvas size
+    ///
+    /// Return the bytes of the PNG file
+    pub fn to_png(&self, scale: f32) -> Result<Vec<u8>, ScreenshotError> {
+        screenshot::encode_png(&self.buffer, self.width, self.height, scale)
+    }
+
+    /// Present the whole canvas to its target
     pub fn update(&mut self) {
-        let data = web_sys::ImageData::new_with_u8_clamped_array_and_sh(Clamped(&mut self.buffer), self.width as u32, self.height as u32).unwrap();
-        self.context.put_image_data(&data, 0.0, 0.0).unwrap();
+        self.target.present(&self.buffer, self.width, self.height);
     }
-}
\ No newline at end of file
+}
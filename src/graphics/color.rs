@@ -42,17 +42,35 @@ impl RGBColor {
         self.a = rhs.a;
     }
 
+    /// Move the color toward another one, keeping its alpha
+    ///
+    /// # Arguments
+    /// * rhs - Color to move toward
+    /// * amount - 0.0 keeps the color, 1.0 gives `rhs`
+    ///
+    /// Return the mixed color
+    pub fn mix(&self, rhs: &RGBColor, amount: f32) -> RGBColor {
+        let mix = |lhs: u8, rhs: u8| (lhs as f32 + (rhs as f32 - lhs as f32) * amount) as u8;
+
+        RGBColor {
+            r: mix(self.r, rhs.r),
+            g: mix(self.g, rhs.g),
+            b: mix(self.b, rhs.b),
+            a: self.a,
+        }
+    }
+
     pub fn from_hsl(hsl: &HSLColor) -> RGBColor {
         let c = (1.0 - (hsl.l * 2.0 - 1.0).abs()) * hsl.s;
         let x = c * (1.0 - ((hsl.h / 60.0) % 2.0 - 1.0).abs());
         let m = hsl.l - c / 2.0;
 
         let (r, g, b) = match hsl.h {
-            h if h >= 0.0 && h < 60.0 => (c, x, 0.0),
-            h if h >= 60.0 && h < 120.0 => (x, c, 0.0),
-            h if h >= 120.0 && h < 180.0 => (0.0, c, x),
-            h if h >= 180.0 && h < 240.0 => (0.0, x, c),
-            h if h >= 240.0 && h < 300.0 => (x, 0.0, c),
+            h if (0.0..60.0).contains(&h) => (c, x, 0.0),
+            h if (60.0..120.0).contains(&h) => (x, c, 0.0),
+            h if (120.0..180.0).contains(&h) => (0.0, c, x),
+            h if (180.0..240.0).contains(&h) => (0.0, x, c),
+            h if (240.0..300.0).contains(&h) => (x, 0.0, c),
             _ => (c, 0.0, x)
         };
 
@@ -1,7 +1,15 @@
 pub use color::{RGBColor, HSLColor};
 pub use canvas::Canvas;
+pub use atlas::{Texture, TextureAtlas, TextureError};
+pub use target::{RenderTarget, MemoryTarget};
+#[cfg(feature = "web")]
+pub use target::BrowserTarget;
 
 mod canvas;
+mod target;
+mod atlas;
 pub mod color;
+#[cfg(feature = "builtin-textures")]
 pub mod textures;
+pub mod screenshot;
 
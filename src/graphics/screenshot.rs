@@ -0,0 +1,105 @@
+use std::fmt;
+
+/// Largest screenshot scale, bounding the size of the image
+pub const MAX_SCALE: f32 = 8.0;
+
+///
+/// Screenshot error
+///
+#[derive(Debug)]
+pub enum ScreenshotError {
+    /// The scale is not a positive number up to [`MAX_SCALE`]
+    InvalidScale(f32),
+    /// The image has no pixel, its width or height being 0
+    Empty,
+    /// The PNG encoder failed
+    Encoding(png::EncodingError),
+}
+
+impl fmt::Display for ScreenshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScreenshotError::InvalidScale(scale) => write!(f, "invalid screenshot scale {}, expected a positive number up to {}", scale, MAX_SCALE),
+            ScreenshotError::Empty => write!(f, "the image is empty"),
+            ScreenshotError::Encoding(err) => write!(f, "PNG encoding failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ScreenshotError {}
+
+impl From<png::EncodingError> for ScreenshotError {
+    fn from(err: png::EncodingError) -> ScreenshotError {
+        ScreenshotError::Encoding(err)
+    }
+}
+
+/// Resize RGBA pixels with the nearest neighbour
+///
+/// # Arguments
+/// * pixels - RGBA pixels, row by row
+/// * width - Source width
+/// * height - Source height
+/// * scale - Size multiplier, e.g. 0.25 for a thumbnail, up to [`MAX_SCALE`]
+///
+/// Return the resized pixels and their size
+pub fn scale_rgba(pixels: &[u8], width: usize, height: usize, scale: f32) -> Result<(Vec<u8>, usize, usize), ScreenshotError> {
+    if !scale.is_finite() || scale <= 0.0 || scale > MAX_SCALE {
+        return Err(ScreenshotError::InvalidScale(scale));
+    }
+    if width == 0 || height == 0 {
+        return Err(ScreenshotError::Empty);
+    }
+    let new_width = ((width as f32 * scale).round() as usize).max(1);
+    let new_height = ((height as f32 * scale).round() as usize).max(1);
+    let mut scaled = Vec::with_capacity(new_width * new_height * 4);
+
+    for y in 0..new_height {
+        let src_y = (y * height / new_height).min(height.saturating_sub(1));
+
+        for x in 0..new_width {
+            let src_x = (x * width / new_width).min(width.saturating_sub(1));
+            let offset = (src_y * width + src_x) * 4;
+
+            scaled.extend_from_slice(&pixels[offset..offset + 4]);
+        }
+    }
+    Ok((scaled, new_width, new_height))
+}
+
+/// Encode RGBA pixels as a PNG image
+///
+/// The alpha channel is dropped, frames are always opaque
+///
+/// # Arguments
+/// * pixels - RGBA pixels, row by row
+/// * width - Image width
+/// * height - Image height
+/// * scale - Size multiplier, 1.0 keeps the original size, up to [`MAX_SCALE`]
+///
+/// Return the bytes of the PNG file
+pub fn encode_png(pixels: &[u8], width: usize, height: usize, scale: f32) -> Result<Vec<u8>, ScreenshotError> {
+    if width == 0 || height == 0 {
+        return Err(ScreenshotError::Empty);
+    }
+    let scaled;
+    let (pixels, width, height) = if scale == 1.0 {
+        (pixels, width, height)
+    } else {
+        scaled = scale_rgba(pixels, width, height, scale)?;
+        (&scaled.0[..], scaled.1, scaled.2)
+    };
+    let rgb: Vec<u8> = pixels.chunks_exact(4).flat_map(|pixel| pixel[..3].iter().copied()).collect();
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut bytes, width as u32, height as u32);
+
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+
+        writer.write_image_data(&rgb)?;
+        writer.finish()?;
+    }
+    Ok(bytes)
+}
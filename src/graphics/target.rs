@@ -0,0 +1,75 @@
+#[cfg(feature = "web")]
+use wasm_bindgen::{JsCast, Clamped};
+
+///
+/// Render target
+///
+/// Destination of the frames drawn in a [`Canvas`](super::Canvas)
+///
+pub trait RenderTarget {
+    /// Present a complete frame
+    ///
+    /// # Arguments
+    /// * buffer - RGBA pixels of the frame, row by row
+    /// * width - Frame width
+    /// * height - Frame height
+    fn present(&mut self, buffer: &[u8], width: usize, height: usize);
+}
+
+///
+/// In-memory render target
+///
+/// Frames stay in the canvas buffer, nothing is sent anywhere.
+/// Used to render outside a browser (tests, tools, servers)
+///
+#[derive(Default)]
+pub struct MemoryTarget;
+
+impl RenderTarget for MemoryTarget {
+    fn present(&mut self, _buffer: &[u8], _width: usize, _height: usize) {}
+}
+
+///
+/// Browser render target
+///
+/// Copies the frames into the HTML5 Canvas with the id `canvas`
+///
+#[cfg(feature = "web")]
+pub struct BrowserTarget {
+    context: web_sys::CanvasRenderingContext2d,
+}
+
+#[cfg(feature = "web")]
+impl BrowserTarget {
+    /// Construct a new browser target
+    ///
+    /// The canvas must exist in the html document
+    /// If it doesn't, it will result in a RuntimeError: "unreachable executed"
+    ///
+    /// # Arguments
+    /// * width - Canvas width
+    /// * height - Canvas height
+    ///
+    /// Return BrowserTarget object
+    pub fn new(width: usize, height: usize) -> BrowserTarget {
+        let window = web_sys::window().expect("no global `window` exists");
+        let document = window.document().expect("should have a document on window");
+        let canvas = document.get_element_by_id("canvas").unwrap().dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
+        let context = canvas.get_context("2d").unwrap().unwrap().dyn_into::<web_sys::CanvasRenderingContext2d>().unwrap();
+
+        canvas.set_width(width as u32);
+        canvas.set_height(height as u32);
+
+        BrowserTarget {
+            context,
+        }
+    }
+}
+
+#[cfg(feature = "web")]
+impl RenderTarget for BrowserTarget {
+    fn present(&mut self, buffer: &[u8], width: usize, height: usize) {
+        let data = web_sys::ImageData::new_with_u8_clamped_array_and_sh(Clamped(buffer), width as u32, height as u32).unwrap();
+        self.context.put_image_data(&data, 0.0, 0.0).unwrap();
+    }
+}
@@ -0,0 +1,794 @@
+use crate::graphics::{Canvas, RGBColor, HSLColor, TextureAtlas};
+use crate::graphics::color::WHITE;
+use crate::engine::{Player, Map, Cell, Direction, Portal, Fog, Enemy, DOOR_VALUE};
+use crate::engine::vectors::{Vector2f, Vector2i, Vector3f};
+use crate::engine::rayobject::{Ray, PORTAL_RECURSION_LIMIT, MAX_DIST};
+use crate::engine::sprite::{Sprite, Zdist, Zorigin};
+use std::cmp::Ordering::{Less, Greater};
+use std::ops::Range;
+
+/// Sprites closer than this to the camera plane are not drawn
+const NEAR_PLANE: f32 = 1e-3;
+/// Owner of a row whose color is known
+const RESOLVED: usize = usize::MAX;
+/// End of a list of translucent colors
+const NONE: usize = usize::MAX;
+/// Columns drawn before being copied to the canvas
+const BATCH: usize = 8;
+/// Cells walked by a column before giving up on its remaining rows
+const MAX_CELLS: usize = 4096;
+
+/// Camera a column is walked from, the player's or a virtual one behind a portal
+#[derive(Clone, Copy)]
+struct View {
+    /// Position, z being the eye height
+    eye: Vector3f,
+    /// Horizontal direction of the column
+    dir: Vector2f,
+    /// Number of portals crossed
+    depth: usize,
+    /// Rotation accumulated through the portals
+    rotation: f32,
+    /// Side of the first portal crossed, in degree
+    portal_degree: f32,
+}
+
+/// Surface hit by some rows of a column
+struct Face<'a> {
+    pos: Vector3f,
+    dir: Direction,
+    value: u32,
+    portals: Option<(Option<&'a Portal>, Option<&'a Portal>)>,
+    /// Index of the cell when the face shows a door frame to rows coming from a door
+    frame: Option<usize>,
+}
+
+/// Where the rows of a face are hit
+#[derive(Clone, Copy)]
+enum Span {
+    /// Vertical face at a fixed distance, `axis` being the world coordinate along it
+    Side { dist: f32, axis: f32 },
+    /// Horizontal plane at a given height, crossed between the distances the cell is entered and left
+    Plane { height: f32, entry: f32, exit: f32 },
+}
+
+/// Row of the column being drawn
+#[derive(Clone, Copy)]
+struct Row {
+    /// Vertical slope of the row: its height at a distance `t` is `eye - t * slope`
+    slope: f32,
+    inverse_slope: f32,
+    /// Cell index and level right after a door
+    door: (usize, f32),
+}
+
+/// What a face gives to a row
+enum Shade {
+    /// The row does not hit the face
+    Miss,
+    /// Final color of the row, at a distance
+    Opaque(RGBColor, f32),
+    /// Color blended with what lies behind, at a distance
+    Translucent(RGBColor, f32),
+    /// Nothing up to a distance, the row shows the background or the fog
+    Void(f32),
+    /// The row goes through the portal of the face
+    Portal,
+}
+
+///
+/// Column renderer
+///
+/// Walks the cells seen by each screen column once and resolves the rows
+/// analytically: side faces are hit at the distance a cell is entered,
+/// tops and bottoms where a row crosses their plane. Gives the same image
+/// as casting one ray per pixel, at a fraction of the cost.
+///
+pub struct Renderer {
+    /// Distance of each pixel, column by column
+    z_buffer: Vec<Zdist>,
+    z_origins: Vec<Zorigin>,
+
+    slopes: Vec<f32>,
+    /// Inverse of the slopes, multiplying is much cheaper than dividing
+    inverse_slopes: Vec<f32>,
+    /// Pitch shear the slopes are computed with, see [`Player::shear`]
+    shear: f32,
+    /// First row looking down, the rows above it look up
+    middle: usize,
+    /// Depth of the walk each row of the column belongs to
+    owner: Vec<usize>,
+    /// Range of rows still open at each depth
+    open: Vec<Range<usize>>,
+    /// Fog seen by the camera of each depth
+    fogs: Vec<Option<Fog>>,
+    /// Cell index and level right after the last door crossed by each row
+    doors: Vec<(usize, f32)>,
+    /// Translucent colors waiting for what lies behind them, with the previous one of the same row
+    pending: Vec<(RGBColor, usize)>,
+    /// Last translucent color of each row
+    pending_head: Vec<usize>,
+    /// First pixel of the column being drawn in the z buffer
+    z_offset: usize,
+    /// Last columns drawn, copied to the canvas row by row
+    batch: Vec<RGBColor>,
+    /// Column being drawn in the batch
+    slot: usize,
+}
+
+impl Renderer {
+    /// Construct a renderer for a canvas size
+    ///
+    /// # Arguments
+    /// * width - Canvas width
+    /// * height - Canvas height
+    ///
+    /// Return Renderer object
+    pub fn new(width: usize, height: usize) -> Renderer {
+        let mut renderer = Renderer {
+            z_buffer: vec![Zdist::default(); width * height],
+            z_origins: Vec::new(),
+
+            slopes: vec![0.0; height],
+            inverse_slopes: vec![0.0; height],
+            shear: 0.0,
+            middle: 0,
+            owner: vec![RESOLVED; height],
+            open: vec![0..0; PORTAL_RECURSION_LIMIT + 1],
+            fogs: vec![None; PORTAL_RECURSION_LIMIT + 1],
+            doors: vec![(NONE, 0.0); height],
+            pending: Vec::new(),
+            pending_head: vec![NONE; height],
+            z_offset: 0,
+            batch: vec![RGBColor::new(0, 0, 0); BATCH * height],
+            slot: 0,
+        };
+
+        renderer.set_shear(0.0);
+        renderer
+    }
+
+    /// Shift the slopes of the rows, moving the horizon
+    ///
+    /// # Arguments
+    /// * shear - Slope added to every row, see [`Player::shear`]
+    fn set_shear(&mut self, shear: f32) {
+        let height = self.slopes.len() as f32;
+
+        for (y, slope) in self.slopes.iter_mut().enumerate() {
+            *slope = 0.5 * (2.0 * y as f32 / height - 1.0) - shear;
+        }
+        for (inverse, slope) in self.inverse_slopes.iter_mut().zip(&self.slopes) {
+            *inverse = 1.0 / slope;
+        }
+        self.middle = self.slopes.partition_point(|&slope| slope < 0.0);
+        self.shear = shear;
+    }
+
+    /// Draw the walls, floors and ceilings seen by the player
+    ///
+    /// # Arguments
+    /// * canvas - Canvas to draw in
+    /// * map - Map to draw
+    /// * atlas - Textures of the map
+    /// * player - Camera
+    pub fn draw_view(&mut self, canvas: &mut Canvas, map: &Map, atlas: &TextureAtlas, player: &Player) {
+        let canvas_width = canvas.width as f32;
+        let eye = player.eye();
+
+        if player.shear() != self.shear {
+            self.set_shear(player.shear());
+        }
+        self.z_origins.clear();
+        for x in 0..canvas.width {
+            let camera_x = 2.0 * x as f32 / canvas_width - 1.0;
+            let dir = Vector2f::new(player.dir.x + player.plane.x * camera_x, player.dir.y + player.plane.y * camera_x);
+            let slot = x % BATCH;
+
+            self.owner.fill(0);
+            self.z_offset = x * canvas.height;
+            self.slot = slot;
+            self.pending.clear();
+            self.open[0] = 0..canvas.height;
+            self.walk(map, atlas, player, View { eye, dir, depth: 0, rotation: 0.0, portal_degree: 0.0 }, None);
+
+            if slot == BATCH - 1 || x == canvas.width - 1 {
+                //Writing whole rows is much faster than going down the canvas
+                for y in 0..canvas.height {
+                    canvas.put_row(x - slot, y, &self.batch[y * BATCH..y * BATCH + slot + 1]);
+                }
+            }
+        }
+    }
+
+    /// Walk the cells of a column and resolve its rows owned by `view.depth`
+    ///
+    /// # Arguments
+    /// * view - Camera of the walk
+    /// * dest - Portal the walk starts behind, cells up to it are skipped
+    fn walk(&mut self, map: &Map, atlas: &TextureAtlas, player: &Player, view: View, dest: Option<Vector3f>) {
+        let mut ray = Ray::horizontal(view.eye, view.dir);
+        let mut skipping = dest;
+        let mut first = true;
+        let height = self.slopes.len();
+        let depth = map.depth() as f32;
+        let eye = view.eye.z;
+        let fog = map.fog(eye - 0.5);
+        //Nothing shows through the fog past its end
+        let max_dist = fog.map_or(MAX_DIST, |fog| fog.end.min(MAX_DIST));
+
+        self.fogs[view.depth] = fog;
+        for index in 0..MAX_CELLS {
+            if self.open[view.depth].is_empty() {
+                return;
+            }
+            let cell = ray.cell();
+
+            if let Some(dest) = skipping {
+                if cell.x == dest.x && cell.y == dest.y {
+                    skipping = None;
+                }
+                ray.grow();
+                continue;
+            }
+            let t0 = ray.entry_dist();
+
+            if first {
+                //Rows starting outside of the map
+                let (above, below) = if t0 > 0.0 {
+                    (self.rows_in(f32::NEG_INFINITY, (eye - depth) / t0, 0..height), self.rows_in(eye / t0, f32::INFINITY, 0..height))
+                } else if eye < 0.0 || eye >= depth {
+                    (0..height, 0..0)
+                } else {
+                    (0..0, 0..0)
+                };
+
+                for rows in [above, below] {
+                    self.shade_rows(view.depth, rows, |row| {
+                        let h0 = eye - t0 * row.slope;
+
+                        if h0 < 0.0 || h0 >= depth { Shade::Void(t0) } else { Shade::Miss }
+                    });
+                }
+                first = false;
+            }
+            if Renderer::leaves_map(map, cell, view.dir) {
+                //Nothing but empty cells from there
+                break;
+            }
+            let far = ray.cell_dist() > max_dist;
+
+            //Looking down, from the eye level to the ground
+            let mut z = floor(eye).min(depth - 1.0);
+            while z >= 0.0 {
+                self.walk_level(map, atlas, player, &view, &ray, index, z, far, true);
+                z -= 1.0;
+            }
+            if eye > 0.0 {
+                self.leave_through(map, atlas, player, &view, &ray, 0.0, true);
+            }
+
+            //Looking up, from the eye level to the ceiling
+            let mut z = floor(eye).max(0.0);
+            while z < depth {
+                self.walk_level(map, atlas, player, &view, &ray, index, z, far, false);
+                z += 1.0;
+            }
+            if eye < depth {
+                self.leave_through(map, atlas, player, &view, &ray, depth, false);
+            }
+            ray.grow();
+        }
+        let dist = ray.entry_dist();
+
+        self.shade_rows(view.depth, 0..height, |_| Shade::Void(dist));
+    }
+
+    /// Whether a column is out of the map and moving away from it
+    fn leaves_map(map: &Map, cell: Vector3f, dir: Vector2f) -> bool {
+        (cell.x < 0.0 && dir.x <= 0.0) || (cell.x >= map.width() as f32 && dir.x >= 0.0) ||
+            (cell.y < 0.0 && dir.y <= 0.0) || (cell.y >= map.height() as f32 && dir.y >= 0.0)
+    }
+
+    /// Resolve the rows hitting one level of the current cell
+    ///
+    /// # Arguments
+    /// * ray - Horizontal ray in the cell
+    /// * index - Number of cells walked so far
+    /// * z - Level
+    /// * far - Whether the cell is past the drawing distance
+    /// * descending - Whether the rows below the horizon are resolved, or the ones above it
+    #[allow(clippy::too_many_arguments)]
+    fn walk_level(&mut self, map: &Map, atlas: &TextureAtlas, player: &Player, view: &View, ray: &Ray, index: usize, z: f32, far: bool, descending: bool) {
+        let t0 = ray.entry_dist();
+        let t1 = ray.exit_dist();
+        let eye = view.eye.z;
+        let rows = self.half(descending);
+        let pos = Vector3f::new(ray.cell().x, ray.cell().y, z);
+
+        match map.get(&pos) {
+            Cell::Empty => {
+                if far {
+                    let candidates = self.rows_between(eye - z - 1.0, eye - z, t0, rows);
+
+                    self.shade_rows(view.depth, candidates, |row| {
+                        if floor(eye - t0 * row.slope) == z { Shade::Void(t0) } else { Shade::Miss }
+                    });
+                }
+            }
+            Cell::Wall { value, height } => {
+                let plane = if descending { z + height } else { z };
+
+                if (descending && eye > plane) || (!descending && eye < plane) {
+                    let dir = if descending { Direction::Up } else { Direction::Down };
+                    let texture = if !descending {
+                        map.surface(&Vector3f::new(pos.x, pos.y, z - 1.0)).ceiling
+                    } else if *height == 1.0 && z + 1.0 < map.depth() as f32 {
+                        //The top of a full wall is the floor of the level above
+                        map.surface(&Vector3f::new(pos.x, pos.y, z + 1.0)).floor
+                    } else {
+                        map.surface(&pos).floor
+                    };
+                    let face = Face { pos, dir, value: texture.unwrap_or(*value), portals: None, frame: None };
+                    let candidates = self.rows_crossing(eye - plane, t0, t1, rows.clone());
+
+                    self.draw_face(map, atlas, player, view, &face, Span::Plane { height: plane, entry: t0, exit: t1 }, candidates, |h0, h1| {
+                        if descending { h0 > plane && h1 <= plane } else { h0 < plane && h1 >= plane }
+                    });
+                }
+                if ray.side() != Direction::None {
+                    let dir = ray.side();
+                    let axis = if dir.is_under_light() { view.eye.x + t0 * view.dir.x } else { view.eye.y + t0 * view.dir.y };
+                    let frame = if *height == 1.0 { Some(index) } else { None };
+                    let face = Face { pos, dir, value: *value, portals: map.portals_at(pos, dir), frame };
+                    let candidates = self.rows_between(eye - z - height, eye - z, t0, rows);
+                    let height = *height;
+
+                    //A full wall covers [z, z + 1) of the level, a partial one (z, z + height]
+                    self.draw_face(map, atlas, player, view, &face, Span::Side { dist: t0, axis }, candidates, |h0, _| {
+                        if height == 1.0 { h0 >= z && h0 < z + 1.0 } else { h0 > z && h0 <= z + height }
+                    });
+                }
+            }
+            Cell::Thin(thin) => {
+                if let Some((dist, axis)) = ray.thin_hit(thin.as_ref()) {
+                    let face = Face { pos, dir: thin.dir(), value: thin.value(), portals: map.portals_at(pos, thin.dir()), frame: None };
+                    let candidates = self.rows_between(eye - z - 1.0, eye - z, dist, rows.clone());
+
+                    self.draw_face(map, atlas, player, view, &face, Span::Side { dist, axis }, candidates, |h, _| h >= z && h <= z + 1.0);
+                }
+                if thin.value() >= DOOR_VALUE {
+                    //Walls right behind a door show its frame
+                    for y in self.rows_between(eye - z - 1.0, eye - z, t1, rows) {
+                        if self.owner[y] == view.depth && floor(eye - t1 * self.slopes[y]) == z {
+                            self.doors[y] = (index + 1, z);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Shade the rows of a column hitting a face
+    ///
+    /// # Arguments
+    /// * face - Surface hit
+    /// * span - Where the face lies
+    /// * candidates - Rows which may hit the face
+    /// * hits - Whether a row entering the cell at h0 and leaving it at h1 hits the face
+    #[allow(clippy::too_many_arguments)]
+    fn draw_face<F: Fn(f32, f32) -> bool>(&mut self, map: &Map, atlas: &TextureAtlas, player: &Player, view: &View, face: &Face, span: Span, candidates: Range<usize>, hits: F) {
+        let eye = view.eye.z;
+        let lit = face.dir.is_under_light();
+        let texture = match atlas.wall(face.value, lit) {
+            Some(texture) => texture,
+            None => return,
+        };
+        let frame = atlas.wall(DOOR_VALUE + 2, lit).unwrap_or(texture);
+        let portal = face.portals.and_then(|_| atlas.get("portal"));
+        let span = match span {
+            Span::Side { dist, axis } => Span::Side { dist, axis: axis - floor(axis) },
+            plane => plane,
+        };
+        let through = self.shade_rows(view.depth, candidates, |row| {
+            let (dist, texture_pos) = match span {
+                Span::Side { dist, axis } => {
+                    let h = eye - dist * row.slope;
+
+                    if !hits(h, h) {
+                        return Shade::Miss;
+                    }
+                    (dist, Vector2f::new(axis, h - floor(h)))
+                }
+                Span::Plane { height, entry, exit } => {
+                    if !hits(eye - entry * row.slope, eye - exit * row.slope) {
+                        return Shade::Miss;
+                    }
+                    let dist = (eye - height) * row.inverse_slope;
+                    let hit_x = -(view.eye.x + dist * view.dir.x);
+                    let hit_y = -(view.eye.y + dist * view.dir.y);
+
+                    (dist, Vector2f::new(hit_x - floor(hit_x), hit_y - floor(hit_y)))
+                }
+            };
+            let texture = match face.frame {
+                Some(index) if row.door == (index, face.pos.z) => frame,
+                _ => texture,
+            };
+            let wall = || texture.texel(texture_pos.x, texture_pos.y);
+
+            let color = match (face.portals, portal) {
+                (Some((source, dest)), Some(portal)) => {
+                    let portal_color = portal.texel(texture_pos.x, texture_pos.y);
+
+                    if portal_color == WHITE {
+                        //Portal's mask
+                        wall()
+                    } else if portal_color.a != 255 && dest.is_some() && view.depth < PORTAL_RECURSION_LIMIT {
+                        //Portal's center
+                        return Shade::Portal;
+                    } else {
+                        //Portal's border
+                        RGBColor::from_hsl(&(HSLColor::from_rgb(&portal_color) + source.unwrap().hsl))
+                    }
+                }
+                //Without overlay the whole face opens on the other side
+                (Some((_, Some(_))), None) if view.depth < PORTAL_RECURSION_LIMIT => return Shade::Portal,
+                _ => wall(),
+            };
+            if color.a != 255 {
+                Shade::Translucent(color, dist)
+            } else {
+                Shade::Opaque(color, dist)
+            }
+        });
+
+        if let (false, Some((Some(source), Some(dest)))) = (through.is_empty(), face.portals) {
+            self.pass_through_portal(map, atlas, player, view, face, source, dest, &through);
+        }
+    }
+
+    /// Hand rows over to the virtual camera behind a portal and walk from there
+    ///
+    /// # Arguments
+    /// * face - Face holding the portal
+    /// * source - Portal the rows enter
+    /// * dest - Portal the rows come out of
+    /// * rows - Rows going through
+    #[allow(clippy::too_many_arguments)]
+    fn pass_through_portal(&mut self, map: &Map, atlas: &TextureAtlas, player: &Player, view: &View, face: &Face, source: &Portal, dest: &Portal, rows: &[usize]) {
+        let depth = view.depth + 1;
+        let link = dest.link_dir(source);
+        let eye = Vector3f::new(dest.link_x(source, &view.eye), dest.link_y(source, &view.eye), dest.pos.z - source.pos.z + view.eye.z);
+        let mut dir = view.dir;
+        let rotation = view.rotation + link;
+        let portal_degree = if view.depth == 0 { face.dir.to_degree() } else { view.portal_degree };
+        let pos = Vector3f::new(eye.x, eye.y, eye.z - 0.5);
+
+        dir.rotate(link);
+        if !self.z_origins.iter().any(|origin| origin.pos == pos && origin.depth == depth) {
+            let mut player_dir = player.dir;
+            let mut player_plane = player.plane;
+
+            player_dir.rotate(rotation);
+            player_plane.rotate(rotation);
+            self.z_origins.push(Zorigin { pos, dir: player_dir, plane: player_plane, depth, rotation, portal_degree });
+        }
+        for &y in rows {
+            self.owner[y] = depth;
+            self.doors[y] = (NONE, 0.0);
+        }
+        self.open[depth] = rows[0]..rows[rows.len() - 1] + 1;
+        self.walk(map, atlas, player, View { eye, dir, depth, rotation, portal_degree }, Some(dest.pos));
+    }
+
+    /// Resolve the rows leaving the map through its floor or its ceiling
+    ///
+    /// Textured surfaces are drawn like the tops and bottoms of walls, the others keep flat colors
+    ///
+    /// # Arguments
+    /// * ray - Horizontal ray in the cell
+    /// * plane - Height of the ground or of the ceiling
+    /// * descending - Whether the rows below the horizon are resolved, or the ones above it
+    #[allow(clippy::too_many_arguments)]
+    fn leave_through(&mut self, map: &Map, atlas: &TextureAtlas, player: &Player, view: &View, ray: &Ray, plane: f32, descending: bool) {
+        let t0 = ray.entry_dist();
+        let t1 = ray.exit_dist();
+        let eye = view.eye.z;
+        let candidates = self.rows_crossing(eye - plane, t0, t1, self.half(descending));
+        let crosses = |h0: f32, h1: f32| if descending { h0 > plane && h1 <= plane } else { h0 < plane && h1 >= plane };
+        let (level, dir) = if descending { (plane, Direction::Up) } else { (plane - 1.0, Direction::Down) };
+        let pos = Vector3f::new(ray.cell().x, ray.cell().y, level);
+        let surface = map.surface(&pos);
+
+        match if descending { surface.floor } else { surface.ceiling } {
+            Some(texture) => {
+                let face = Face { pos, dir, value: texture, portals: None, frame: None };
+
+                self.draw_face(map, atlas, player, view, &face, Span::Plane { height: plane, entry: t0, exit: t1 }, candidates, crosses);
+            }
+            None => {
+                self.shade_rows(view.depth, candidates, |row| {
+                    if crosses(eye - t0 * row.slope, eye - t1 * row.slope) {
+                        Shade::Opaque(background(row.slope), (eye - plane) * row.inverse_slope)
+                    } else {
+                        Shade::Miss
+                    }
+                });
+            }
+        }
+    }
+
+    /// Shade the candidate rows still open at a depth
+    ///
+    /// Opaque colors end the rows, behind the translucent ones in front of them, all seen through the fog
+    ///
+    /// # Arguments
+    /// * depth - Depth of the walk
+    /// * candidates - Rows to shade
+    /// * shade - What each row gets
+    ///
+    /// Return the rows going through a portal
+    fn shade_rows<F: FnMut(Row) -> Shade>(&mut self, depth: usize, candidates: Range<usize>, mut shade: F) -> Vec<usize> {
+        //Slices are kept in registers, unlike the vectors behind self
+        let slopes = &self.slopes[..];
+        let inverse_slopes = &self.inverse_slopes[..];
+        let owner = &mut self.owner[..];
+        let doors = &mut self.doors[..];
+        let pending = &mut self.pending;
+        let pending_head = &mut self.pending_head[..];
+        let slot = self.slot;
+        let batch = &mut self.batch[..];
+        let dists = &mut self.z_buffer[self.z_offset..self.z_offset + slopes.len()];
+        let fog = self.fogs[depth];
+        let mut through = Vec::new();
+        let open = &mut self.open[depth];
+        let candidates = candidates.start.max(open.start)..candidates.end.min(open.end);
+
+        for y in candidates {
+            if owner[y] != depth {
+                continue;
+            }
+            let (mut color, dist) = match shade(Row { slope: slopes[y], inverse_slope: inverse_slopes[y], door: doors[y] }) {
+                Shade::Miss => continue,
+                Shade::Opaque(color, dist) => (fog.map_or(color, |fog| fog.apply(color, dist.abs())), dist),
+                Shade::Void(dist) => (fog.map_or_else(|| background(slopes[y]), |fog| fog.color), dist),
+                Shade::Translucent(color, dist) => {
+                    pending.push((fog.map_or(color, |fog| fog.apply(color, dist.abs())), pending_head[y]));
+                    pending_head[y] = pending.len() - 1;
+                    continue;
+                }
+                Shade::Portal => {
+                    through.push(y);
+                    continue;
+                }
+            };
+            let mut front = pending_head[y];
+
+            while front != NONE {
+                let (mut translucent, next) = pending[front];
+
+                translucent.blend(&color);
+                color = translucent;
+                front = next;
+            }
+            batch[y * BATCH + slot] = color;
+            dists[y] = Zdist { dist: dist.abs(), portal_depth: depth };
+            //Leave the row ready for the next column
+            owner[y] = RESOLVED;
+            doors[y] = (NONE, 0.0);
+            pending_head[y] = NONE;
+        }
+        while open.start < open.end && owner[open.start] != depth {
+            open.start += 1;
+        }
+        while open.start < open.end && owner[open.end - 1] != depth {
+            open.end -= 1;
+        }
+        through
+    }
+
+    /// Rows looking down, below the horizon, or the ones looking up
+    fn half(&self, descending: bool) -> Range<usize> {
+        if descending { self.middle..self.slopes.len() } else { 0..self.middle }
+    }
+
+    /// Rows of a group whose slope lies in `[low / dist, high / dist]`, with a row of margin
+    fn rows_between(&self, low: f32, high: f32, dist: f32, rows: Range<usize>) -> Range<usize> {
+        if dist <= 0.0 {
+            return rows;
+        }
+        self.rows_in(low / dist, high / dist, rows)
+    }
+
+    /// Rows of a group whose height reaches `eye - offset` between the distances `t0` and `t1`
+    fn rows_crossing(&self, offset: f32, t0: f32, t1: f32, rows: Range<usize>) -> Range<usize> {
+        let (a, b) = (offset / t0, offset / t1);
+
+        self.rows_in(a.min(b), a.max(b), rows)
+    }
+
+    /// Rows of a group whose slope lies in `[low, high]`, with a row of margin
+    fn rows_in(&self, low: f32, high: f32, rows: Range<usize>) -> Range<usize> {
+        let height = self.slopes.len() as f32;
+        //Conversions truncate and saturate, the margin makes up for it
+        let start = (low + self.shear + 0.5) * height - 2.0;
+        let end = (high + self.shear + 0.5) * height + 3.0;
+
+        if start.is_nan() || end.is_nan() || start >= end {
+            return rows.start..rows.start;
+        }
+        let start = (start as usize).clamp(rows.start, rows.end);
+        let end = (end as usize).clamp(start, rows.end);
+
+        start..end
+    }
+
+    /// Draw the sprites over the view, as seen from the player and through the portals
+    ///
+    /// # Arguments
+    /// * canvas - Canvas to draw in
+    /// * map - Map the sprites are in, for its fog
+    /// * atlas - Textures of the sprites
+    /// * sprites - Sprites of the map
+    /// * enemies - Enemies drawn by the sprites
+    /// * player - Camera
+    pub fn draw_sprites(&self, canvas: &mut Canvas, map: &Map, atlas: &TextureAtlas, sprites: &mut [Sprite], enemies: &[Enemy], player: &Player) {
+        for idx in (0..self.z_origins.len()).rev() {
+            let new_origin = self.z_origins[idx];
+            let fog = map.fog(new_origin.pos.z);
+
+            self.draw_sprite(canvas, atlas, sprites, enemies, player, fog, new_origin.depth, new_origin.pos, new_origin.dir, new_origin.plane, new_origin.rotation, new_origin.portal_degree);
+        }
+        //Sprites are seen from half a cell below the eyes
+        let eye = player.eye();
+        let camera = Vector3f::new(eye.x, eye.y, eye.z - 0.5);
+
+        self.draw_sprite(canvas, atlas, sprites, enemies, player, map.fog(player.pos.z), 0, camera, player.dir, player.plane, 0.0, 0.0);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn draw_sprite(&self, canvas: &mut Canvas, atlas: &TextureAtlas, sprites: &mut [Sprite], enemies: &[Enemy], player: &Player, fog: Option<Fog>, depth: usize, pos: Vector3f, dir: Vector2f, plane: Vector2f, rotation: f32, shift_degree: f32) {
+        let canvas_width = canvas.width as i32;
+        let canvas_height = canvas.height as i32;
+        //The pitch moves the sprites along with the horizon
+        let shift = self.shear * canvas_height as f32;
+
+        for sprite in sprites.iter_mut() {
+            if sprite.is_player {
+                sprite.pos = player.pos;
+            }
+            sprite.dist = (pos.x - sprite.pos.x).powf(2.0) + (pos.y - sprite.pos.y).powf(2.0);
+        }
+        sprites.sort_by(|l, r| {
+            if l.is_player && l.dist == r.dist {
+                Greater
+            } else if r.is_player && l.dist == r.dist {
+                Less
+            } else {
+                r.dist.partial_cmp(&l.dist).unwrap()
+            }
+        });
+
+        for sprite in sprites.iter() {
+            let relative_pos = Vector2f::new(sprite.pos.x - pos.x, sprite.pos.y - pos.y);
+            let transform = Vector2f::new((dir.y * relative_pos.x - dir.x * relative_pos.y) * 1.0 / (plane.x * dir.y - dir.x * plane.y),
+                                          (-plane.y * relative_pos.x + plane.x * relative_pos.y) * 1.0 / (plane.x * dir.y - dir.x * plane.y));
+            if transform.y < NEAR_PLANE {
+                continue;
+            }
+            //Texture and the rectangle of it showing the sprite
+            let (texture, origin, size) = if sprite.is_player {
+                let texture = match atlas.get("soldier") {
+                    Some(texture) => texture,
+                    None => continue,
+                };
+                let cell = (texture.width() / 8).max(1);
+
+                (texture, (cell * soldier_view(player, rotation, shift_degree), cell * player.frame), (cell, cell))
+            } else if let Some(idx) = sprite.enemy {
+                let texture = match atlas.get("soldier") {
+                    Some(texture) => texture,
+                    None => continue,
+                };
+                let cell = (texture.width() / 8).max(1);
+                let enemy = &enemies[idx];
+                let (row, directional) = enemy.frame(texture.height() / cell);
+                let column = if directional { enemy_view(enemy, pos) } else { 0 };
+
+                (texture, (cell * column, cell * row), (cell, cell))
+            } else {
+                match atlas.sprite(sprite.value + 1) {
+                    Some(texture) => (texture, (0, 0), (texture.width(), texture.height())),
+                    None => continue,
+                }
+            };
+            let sprite_canvas_x = ((canvas.width / 2) as f32 * (1.0 + transform.x / transform.y)) as i32;
+            let sprite_size = (canvas_height as f32 / transform.y).abs() as i32;
+            let x_bounds = Vector2i::new(sprite_canvas_x - sprite_size / 2, sprite_size / 2 + sprite_canvas_x).clamp(0, canvas_width, 0, canvas_width);
+            let draw_end_y = ((canvas.height / 2) as f32 + shift + sprite_size as f32 * (pos.z + 0.5) - sprite_size as f32 * sprite.pos.z) as i32;
+            let y_bounds = Vector2i::new(draw_end_y - sprite_size, draw_end_y).clamp(0, canvas_height, 0, canvas_height);
+
+            for x in x_bounds.x..x_bounds.y {
+                let text_x = ((x - (sprite_canvas_x - sprite_size / 2)) * size.0 as i32 / sprite_size) as u32;
+
+                if x > 0 && x < canvas_width {
+                    let step = size.1 as f32 / sprite_size as f32;
+                    let mut text_pos = ((y_bounds.x as f32 - sprite_size as f32 * (pos.z - sprite.pos.z)) - canvas_height as f32 / 2.0 - shift + sprite_size as f32 / 2.0) * step;
+
+                    for y in y_bounds.x..y_bounds.y {
+                        let text_y = text_pos as u32 % size.1;
+
+                        let z = &self.z_buffer[(x * canvas_height + y) as usize];
+
+                        text_pos += step;
+                        if (depth != z.portal_depth && !sprite.is_player) || transform.y > z.dist {
+                            continue;
+                        }
+                        let color = texture.pixel(origin.0 + text_x, origin.1 + text_y);
+
+                        if color.a != 0 {
+                            canvas.put_pixel(x as usize, y as usize, fog.map_or(color, |fog| fog.apply(color, transform.y)));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Column of the player sheet showing the player from a camera
+///
+/// # Arguments
+/// * player - Player seen
+/// * rotation - Rotation of the camera behind portals (Radian)
+/// * shift_degree - Orientation of the portal the camera looks through (Degree)
+fn soldier_view(player: &Player, rotation: f32, shift_degree: f32) -> u32 {
+    let mut rotation_degree = (rotation * 180.0 / std::f32::consts::PI).round() % 360.0;
+    let mut player_degree = ((player.dir.y / player.dir.x).atan() * 180.0 / std::f32::consts::PI).round();
+
+    if rotation_degree < 0.0 {
+        rotation_degree += 360.0;
+    }
+
+    if player.dir.x > 0.0 && player.dir.y != 0.0 {
+        player_degree += 180.0;
+    } else if player.dir.x < 0.0 && player.dir.y > 0.0 {
+        player_degree += 360.0;
+    };
+
+    let mut value = -(4 + ((rotation_degree / 45.0).round() - ((player_degree - shift_degree) / 45.0).round()) as i32) % 8;
+    if value < 0 {
+        value += 8;
+    }
+    value as u32
+}
+
+/// Column of the enemy sheet showing an enemy from a camera
+///
+/// # Arguments
+/// * enemy - Enemy seen
+/// * camera - Position of the camera
+fn enemy_view(enemy: &Enemy, camera: Vector3f) -> u32 {
+    let to_camera = (camera.y - enemy.pos.y).atan2(camera.x - enemy.pos.x);
+    let facing = enemy.dir.y.atan2(enemy.dir.x);
+    let eighths = ((facing - to_camera) / std::f32::consts::FRAC_PI_4).round() as i32;
+
+    eighths.rem_euclid(8) as u32
+}
+
+/// Color of a row which hits nothing, the floor's below the horizon
+fn background(slope: f32) -> RGBColor {
+    if slope > 0.0 {
+        RGBColor::new(113, 113, 113)
+    } else {
+        RGBColor::new(56, 56, 56)
+    }
+}
+
+/// Largest integer less than or equal to a number, much faster than `f32::floor` without SSE4.1
+fn floor(value: f32) -> f32 {
+    let truncated = value as i32 as f32;
+
+    if truncated > value { truncated - 1.0 } else { truncated }
+}
@@ -0,0 +1,264 @@
+use wasm_bindgen::prelude::*;
+
+use crate::graphics::BrowserTarget;
+
+#[allow(unused_macros)]
+macro_rules! log {
+    ( $( $t:tt )* ) => {
+        web_sys::console::log_1(&format!( $( $t )* ).into());
+    }
+}
+
+fn to_error(message: &str) -> JsValue {
+    js_sys::Error::new(message).into()
+}
+
+fn to_json(value: &JsValue) -> Result<serde_json::Value, JsValue> {
+    let json = js_sys::JSON::stringify(value)?.as_string().ok_or_else(|| to_error("map is not serializable"))?;
+
+    serde_json::from_str(&json).map_err(|err| to_error(&err.to_string()))
+}
+
+///
+/// Browser bindings
+///
+/// Wraps the portable [`Game`](crate::Game) and renders into the HTML5 Canvas
+///
+#[wasm_bindgen()]
+pub struct Game {
+    game: crate::Game,
+}
+
+#[wasm_bindgen()]
+impl Game {
+    /// Construct the game from a map description
+    ///
+    /// Throws an `Error` naming the faulty field when the map is invalid,
+    /// e.g. `cells[1][42].direction: unknown variant ...`
+    #[wasm_bindgen(constructor)]
+    pub fn new(map: &JsValue, width: usize, height: usize) -> Result<Game, JsValue> {
+        let map = to_json(map)?;
+        let game = crate::Game::with_target(&map, width, height, Box::new(BrowserTarget::new(width, height)))
+            .map_err(|err| to_error(&err.to_string()))?;
+
+        Ok(Game {
+            game,
+        })
+    }
+
+    /// Construct the game with its own textures, see [`TextureAtlas`]
+    pub fn with_atlas(map: &JsValue, atlas: TextureAtlas, width: usize, height: usize) -> Result<Game, JsValue> {
+        let map = to_json(map)?;
+        let game = crate::Game::with_atlas(&map, atlas.atlas, width, height, Box::new(BrowserTarget::new(width, height)))
+            .map_err(|err| to_error(&err.to_string()))?;
+
+        Ok(Game {
+            game,
+        })
+    }
+
+    /// Construct the game playing the maps of a campaign in order, e.g. `{maps: [map1, map2]}`
+    ///
+    /// The textures compiled in are used when no atlas is given, which is an error without them
+    pub fn with_campaign(campaign: &JsValue, atlas: Option<TextureAtlas>, width: usize, height: usize) -> Result<Game, JsValue> {
+        let campaign = to_json(campaign)?;
+        let atlas = match atlas {
+            Some(atlas) => atlas.atlas,
+            #[cfg(feature = "builtin-textures")]
+            None => crate::graphics::TextureAtlas::builtin(),
+            #[cfg(not(feature = "builtin-textures"))]
+            None => return Err(to_error("an atlas is required, the textures are not compiled in")),
+        };
+        let game = crate::Game::with_campaign(&campaign, atlas, width, height, Box::new(BrowserTarget::new(width, height)))
+            .map_err(|err| to_error(&err.to_string()))?;
+
+        Ok(Game {
+            game,
+        })
+    }
+
+    pub fn process_event(&mut self, key: u32, pressed: bool) {
+        self.game.process_event(key, pressed);
+    }
+
+    /// Press or release a key given by its `KeyboardEvent.code`, e.g. "KeyW"
+    pub fn process_key(&mut self, code: &str, pressed: bool) {
+        self.game.process_key(&crate::engine::Key::from(code), pressed);
+    }
+
+    /// Turn the view and look up or down with a mouse move, e.g. `movementX` and `movementY` of a `mousemove` under pointer lock
+    pub fn process_mouse_move(&mut self, dx: f32, dy: f32) {
+        self.game.process_mouse_move(dx, dy);
+    }
+
+    /// Vertical angle of the view, positive looking up (Radian)
+    pub fn pitch(&self) -> f32 {
+        self.game.pitch()
+    }
+
+    /// Look up or down, e.g. back at the horizon, within `MAX_PITCH` either way
+    pub fn set_pitch(&mut self, angle: f32) {
+        self.game.set_pitch(angle);
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        self.game.update(delta);
+    }
+
+    /// Mouse settings, e.g. `{sensitivity: 0.0025, smoothing: 0, invert_x: false, invert_y: false}`
+    pub fn mouse(&self) -> Result<JsValue, JsValue> {
+        let json = serde_json::to_string(self.game.mouse()).map_err(|err| to_error(&err.to_string()))?;
+
+        js_sys::JSON::parse(&json)
+    }
+
+    /// Replace the mouse settings, the missing ones keeping their default
+    pub fn set_mouse(&mut self, mouse: &JsValue) -> Result<(), JsValue> {
+        let mouse = crate::engine::MouseSettings::from_json(&to_json(mouse)?).map_err(|err| to_error(&err.to_string()))?;
+
+        self.game.set_mouse(mouse);
+        Ok(())
+    }
+
+    /// Move a gamepad stick axis, "move_x", "move_y", "look_x" or "look_y", from -1 to 1
+    pub fn process_axis(&mut self, axis: &str, value: f32) -> Result<(), JsValue> {
+        let axis: crate::engine::Axis = serde_json::from_value(serde_json::Value::String(axis.to_string())).map_err(|err| to_error(&err.to_string()))?;
+
+        self.game.process_axis(axis, value);
+        Ok(())
+    }
+
+    /// Press or release a gamepad button given by its index in `Gamepad.buttons`
+    pub fn process_button(&mut self, button: u32, pressed: bool) {
+        self.game.process_button(button, pressed);
+    }
+
+    /// Gamepad settings, e.g. `{dead_zone: 0.15, curve: 2, invert_y: false}`
+    pub fn gamepad(&self) -> Result<JsValue, JsValue> {
+        let json = serde_json::to_string(self.game.gamepad()).map_err(|err| to_error(&err.to_string()))?;
+
+        js_sys::JSON::parse(&json)
+    }
+
+    /// Replace the gamepad settings, the missing ones keeping their default
+    pub fn set_gamepad(&mut self, gamepad: &JsValue) -> Result<(), JsValue> {
+        let gamepad = crate::engine::GamepadSettings::from_json(&to_json(gamepad)?).map_err(|err| to_error(&err.to_string()))?;
+
+        self.game.set_gamepad(gamepad);
+        Ok(())
+    }
+
+    /// Keys bound to each action, e.g. `{move_forward: ["KeyW", "ArrowUp", 87], fire: ["ControlLeft"]}`
+    pub fn bindings(&self) -> Result<JsValue, JsValue> {
+        js_sys::JSON::parse(&self.game.bindings().to_json().to_string())
+    }
+
+    /// Replace the key bindings by ones saved from [`bindings`](Game::bindings)
+    ///
+    /// Throws an `Error` naming the faulty field, e.g. `fire[1]: the key is already bound to jump`
+    pub fn set_bindings(&mut self, bindings: &JsValue) -> Result<(), JsValue> {
+        let bindings = crate::engine::Bindings::from_json(&to_json(bindings)?).map_err(|err| to_error(&err.to_string()))?;
+
+        self.game.set_bindings(bindings);
+        Ok(())
+    }
+
+    /// Events of the last update, e.g. `[{type: "fired", enemy: 0, damage: 8}]`
+    pub fn events(&self) -> Result<JsValue, JsValue> {
+        let json = serde_json::to_string(self.game.events()).map_err(|err| to_error(&err.to_string()))?;
+
+        js_sys::JSON::parse(&json)
+    }
+
+    /// State of the player, e.g. `{health: 100, armor: 0, alive: true, weapon: "pistol", ammo: 8, keys: [], treasure: 0}`
+    pub fn status(&self) -> Result<JsValue, JsValue> {
+        let json = serde_json::to_string(&self.game.status()).map_err(|err| to_error(&err.to_string()))?;
+
+        js_sys::JSON::parse(&json)
+    }
+
+    /// Statistics of the level once it is finished, `null` before, e.g.
+    /// `{time: 83.5, par_time: 90, kills: {found: 4, total: 5}, secrets: {found: 1, total: 2}, treasure: {found: 7, total: 9}}`
+    pub fn stats(&self) -> Result<JsValue, JsValue> {
+        let json = serde_json::to_string(&self.game.stats()).map_err(|err| to_error(&err.to_string()))?;
+
+        js_sys::JSON::parse(&json)
+    }
+
+    /// Bring the player back to life at the last checkpoint, or where the map starts
+    pub fn respawn(&mut self) {
+        self.game.respawn();
+    }
+
+    /// Load the next map of the campaign, keeping the health, armor, treasure, weapons and ammunition
+    ///
+    /// Return false once the campaign is over
+    pub fn next_level(&mut self) -> Result<bool, JsValue> {
+        self.game.next_level().map_err(|err| to_error(&err.to_string()))
+    }
+
+    /// Index of the map played in the campaign
+    pub fn level(&self) -> usize {
+        self.game.level()
+    }
+
+    /// Whether the player reached an exit, the `exited` event
+    pub fn is_finished(&self) -> bool {
+        self.game.is_finished()
+    }
+
+    /// PNG bytes of the last frame, as a `Uint8Array`
+    ///
+    /// `scale` resizes the image, e.g. 0.25 for a thumbnail, up to 8; the canvas size is kept when omitted
+    pub fn screenshot(&self, scale: Option<f32>) -> Result<Vec<u8>, JsValue> {
+        self.game.screenshot(scale.unwrap_or(1.0)).map_err(|err| to_error(&err.to_string()))
+    }
+}
+
+///
+/// Browser bindings
+///
+/// Wraps the [`TextureAtlas`](crate::graphics::TextureAtlas) filled from JS, e.g.
+/// `atlas.add_png("brick", new Uint8Array(await (await fetch("brick.png")).arrayBuffer()))`
+///
+#[wasm_bindgen()]
+pub struct TextureAtlas {
+    atlas: crate::graphics::TextureAtlas,
+}
+
+#[wasm_bindgen()]
+impl TextureAtlas {
+    /// Construct an empty atlas, or one holding the textures compiled in when `builtin` is true
+    #[wasm_bindgen(constructor)]
+    pub fn new(builtin: bool) -> TextureAtlas {
+        let atlas = match builtin {
+            #[cfg(feature = "builtin-textures")]
+            true => crate::graphics::TextureAtlas::builtin(),
+            _ => crate::graphics::TextureAtlas::new(),
+        };
+
+        TextureAtlas {
+            atlas,
+        }
+    }
+
+    /// Add a texture from RGBA bytes, e.g. the `data` of an `ImageData`
+    pub fn add_rgba(&mut self, name: &str, width: u32, height: u32, rgba: &[u8]) -> Result<(), JsValue> {
+        self.atlas.add_rgba(name, width, height, rgba).map(|_| ()).map_err(|err| to_error(&err.to_string()))
+    }
+
+    /// Add a texture from the bytes of a PNG file
+    pub fn add_png(&mut self, name: &str, bytes: &[u8]) -> Result<(), JsValue> {
+        self.atlas.add_png(name, bytes).map(|_| ()).map_err(|err| to_error(&err.to_string()))
+    }
+
+    /// Map a cell value to a texture, and optionally to another one for the sides in the shadow
+    pub fn bind_wall(&mut self, value: u32, light: &str, dark: Option<String>) -> Result<(), JsValue> {
+        self.atlas.bind_wall(value, light, dark.as_deref()).map_err(|err| to_error(&err.to_string()))
+    }
+
+    /// Map a sprite index to a texture
+    pub fn bind_sprite(&mut self, index: u32, name: &str) -> Result<(), JsValue> {
+        self.atlas.bind_sprite(index, name).map_err(|err| to_error(&err.to_string()))
+    }
+}
@@ -0,0 +1,29 @@
+//! Helpers shared by the test files
+//!
+//! Each file uses its own share of them.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::PathBuf;
+
+use wolf3d_wasm::Game;
+
+pub const WIDTH: usize = 128;
+pub const HEIGHT: usize = 96;
+
+pub fn root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// Description of a map of `tests/fixtures`
+pub fn map(fixture: &str) -> serde_json::Value {
+    let path = root().join("tests/fixtures").join(format!("{}.json", fixture));
+    let json = fs::read_to_string(&path).unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+
+    serde_json::from_str(&json).unwrap()
+}
+
+/// Game playing a map of `tests/fixtures`, drawn in memory
+pub fn load(fixture: &str) -> Game {
+    Game::headless(&map(fixture), WIDTH, HEIGHT).unwrap_or_else(|err| panic!("{}: {}", fixture, err))
+}
@@ -0,0 +1,180 @@
+//! Gameplay checks
+//!
+//! Each case plays a fixture map for some time and checks what the player,
+//! the enemies and the events went through, without looking at the frames.
+
+mod common;
+
+use std::f32::consts::PI;
+
+use wolf3d_wasm::{Game, MemoryTarget};
+use wolf3d_wasm::graphics::TextureAtlas;
+use wolf3d_wasm::engine::{EnemyState, Event, Stats, Status, Tally};
+use wolf3d_wasm::engine::vectors::Vector3f;
+
+use common::{load, map, HEIGHT, WIDTH};
+
+/// Key code of the Fire action
+const FIRE: u32 = 17;
+/// Key code of the Interact action
+const INTERACT: u32 = 70;
+
+fn stand(game: &mut Game, x: f32, y: f32, z: f32, angle: f32) {
+    game.set_camera(Vector3f::new(x, y, z), angle);
+    game.update(0.0);
+}
+
+#[test]
+fn chase_out_of_sight() {
+    let mut map = map("chase");
+
+    map["portals"] = serde_json::json!([]);
+    let mut game = Game::headless(&map, WIDTH, HEIGHT).unwrap();
+
+    game.set_camera(Vector3f::new(5.5, 2.5, 0.0), 0.05);
+    game.update(0.0);
+    assert_eq!(game.events(), &[Event::Alerted { enemy: 0 }]);
+    //Behind the wall the player cannot be seen, the guard goes where it was last seen and waits
+    for _ in 0..90 {
+        game.set_camera(Vector3f::new(2.5, 2.5, 0.0), PI + 0.05);
+        game.update(1.0 / 30.0);
+        assert_eq!(game.events(), &[]);
+    }
+    let enemy = &game.enemies()[0];
+
+    assert!((enemy.pos.x - 5.5).abs() < 0.1, "enemy went to {:?}", enemy.pos);
+    assert_eq!(enemy.state, EnemyState::Chase);
+}
+
+#[test]
+fn shoot_through_portal() {
+    let mut game = load("chase");
+    let mut events = Vec::new();
+
+    //The guard is behind the wall, in the line of fire through the portal
+    game.set_camera(Vector3f::new(2.5, 2.5, 0.0), PI);
+    game.process_event(FIRE, true);
+    for _ in 0..30 {
+        game.update(1.0 / 10.0);
+        events.extend_from_slice(game.events());
+    }
+    assert_eq!(events.iter().filter(|event| matches!(event, Event::Shot { weapon: 1 })).count(), 8);
+    assert_eq!(events.iter().filter(|event| matches!(event, Event::Hit { enemy: 0, damage: 12 })).count(), 2);
+    assert!(events.contains(&Event::Killed { enemy: 0 }));
+    assert!(game.enemies()[0].is_down());
+    assert_eq!(game.weapons().ammo("bullets"), 0);
+}
+
+#[test]
+fn shot_stopped_by_solid_sprite() {
+    let mut map = map("chase");
+
+    //A pillar between the player and the portal
+    map["sprites"] = serde_json::json!([{"index": 3, "pos": {"x": 1, "y": 2, "z": 0}, "solid": true}]);
+    let mut game = Game::headless(&map, WIDTH, HEIGHT).unwrap();
+    let mut events = Vec::new();
+
+    game.set_camera(Vector3f::new(2.5, 2.5, 0.0), PI);
+    game.process_event(FIRE, true);
+    for _ in 0..30 {
+        game.update(1.0 / 10.0);
+        events.extend_from_slice(game.events());
+    }
+    assert_eq!(events.iter().filter(|event| matches!(event, Event::Shot { weapon: 1 })).count(), 8);
+    assert!(!events.iter().any(|event| matches!(event, Event::Hit { .. } | Event::Killed { .. })));
+    assert_eq!(game.enemies()[0].health, 25);
+}
+
+#[test]
+fn campaign() {
+    let mut first = map("pickups");
+    let mut second = map("pushwalls");
+
+    first["cells"][0].as_array_mut().unwrap().push(serde_json::json!({"pos": {"x": 1, "y": 3}, "exit": true}));
+    //The switch at the end of the room
+    second["cells"][0].as_array_mut().unwrap().iter_mut()
+        .find(|cell| cell["pos"] == serde_json::json!({"x": 8, "y": 2})).unwrap()["exit"] = true.into();
+    let campaign = serde_json::json!({"maps": [first, second]});
+    let mut game = Game::with_campaign(&campaign, TextureAtlas::builtin(), WIDTH, HEIGHT, Box::new(MemoryTarget)).unwrap();
+
+    stand(&mut game, 3.5, 1.5, 0.0, 0.05);
+    stand(&mut game, 3.5, 3.5, 0.0, 0.05);
+    assert!(!game.is_finished());
+    stand(&mut game, 1.5, 3.5, 0.0, 0.05);
+    assert_eq!(game.events(), [Event::Exited]);
+    assert!(game.is_finished());
+    //Frozen, the armor is left where it lies
+    stand(&mut game, 5.5, 3.5, 0.0, 0.05);
+    assert_eq!(game.events(), []);
+
+    assert_eq!(game.next_level(), Ok(true));
+    assert_eq!((game.level(), game.is_finished()), (1, false));
+    //Keys are left behind
+    assert_eq!(game.status(), Status { health: 100, armor: 0, alive: true, weapon: Some("machine_gun".to_string()), ammo: Some(14), keys: Vec::new(), treasure: 0 });
+
+    game.set_camera(Vector3f::new(7.2, 2.5, 0.0), 0.05);
+    game.process_event(INTERACT, true);
+    game.update(0.0);
+    game.process_event(INTERACT, false);
+    assert_eq!(game.events(), [Event::Exited]);
+    assert_eq!(game.next_level(), Ok(false));
+    assert_eq!(game.level(), 1);
+}
+
+#[test]
+fn next_level_when_dead() {
+    let campaign = serde_json::json!({"maps": [map("hazards"), map("pushwalls")]});
+    let mut game = Game::with_campaign(&campaign, TextureAtlas::builtin(), WIDTH, HEIGHT, Box::new(MemoryTarget)).unwrap();
+
+    game.set_camera(Vector3f::new(4.5, 2.5, 0.0), PI + 0.05);
+    for _ in 0..25 {
+        game.update(1.0 / 10.0);
+    }
+    assert!(!game.status().alive);
+
+    //Skipping the level brings the player back like a respawn
+    assert_eq!(game.next_level(), Ok(true));
+    assert_eq!(game.status(), Status { health: 100, armor: 0, alive: true, weapon: Some("pistol".to_string()), ammo: Some(8), keys: Vec::new(), treasure: 0 });
+}
+
+#[test]
+fn level_stats() {
+    let mut map = map("pushwalls");
+
+    map["par_time"] = 90.0.into();
+    map["cells"][0].as_array_mut().unwrap().push(serde_json::json!({"pos": {"x": 1, "y": 3}, "exit": true}));
+    map["sprites"].as_array_mut().unwrap().extend([
+        serde_json::json!({"index": 9, "pos": {"x": 2, "y": 1, "z": 0}, "pickup": {"kind": "treasure", "amount": 100}}),
+        serde_json::json!({"index": 9, "pos": {"x": 6, "y": 3, "z": 0}, "pickup": {"kind": "treasure", "amount": 100}}),
+    ]);
+    //Looking away from the player
+    map["enemies"] = serde_json::json!([{"pos": {"x": 6, "y": 1, "z": 0}, "angle": 0, "health": 10}]);
+    let mut game = Game::headless(&map, WIDTH, HEIGHT).unwrap();
+
+    game.set_camera(Vector3f::new(2.2, 2.5, 0.0), 0.05);
+    game.process_event(INTERACT, true);
+    game.update(0.5);
+    game.process_event(INTERACT, false);
+    assert_eq!(game.events(), [Event::Secret]);
+
+    game.set_camera(Vector3f::new(2.5, 1.6, 0.0), -0.025);
+    game.process_event(FIRE, true);
+    game.update(0.5);
+    game.process_event(FIRE, false);
+    assert!(game.events().contains(&Event::Killed { enemy: 0 }));
+    assert_eq!(game.stats(), None);
+
+    game.set_camera(Vector3f::new(1.5, 3.5, 0.0), 0.05);
+    game.update(0.5);
+    assert_eq!(game.events(), [Event::Exited]);
+    assert_eq!(game.stats(), Some(Stats {
+        time: 1.5,
+        par_time: Some(90.0),
+        kills: Tally::new(1, 1),
+        secrets: Tally::new(1, 1),
+        treasure: Tally::new(1, 2),
+    }));
+    //The clock stops with the level
+    game.update(0.5);
+    assert_eq!(game.stats().unwrap().time, 1.5);
+}
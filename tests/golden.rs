@@ -0,0 +1,473 @@
+//! Golden-image tests of the ray caster
+//!
+//! Each case renders a fixture map of `tests/fixtures` from a camera pose
+//! and compares the frame with its reference in `tests/golden`.
+//!
+//! Run with `GOLDEN_BLESS=1` to (re)write the references. On failure the
+//! frame and a diff image (differing pixels in red) are written in
+//! `target/golden`.
+//!
+//! Poses avoid exactly axis-aligned angles: the center column ray would
+//! have a null component, a case the ray caster does not handle.
+
+mod common;
+
+use std::f32::consts::PI;
+use std::fs;
+
+use wolf3d_wasm::{Game, MemoryTarget};
+use wolf3d_wasm::graphics::TextureAtlas;
+use wolf3d_wasm::engine::{Axis, EnemyState, Event, Key, MouseSettings, Status, MAX_HEALTH};
+use wolf3d_wasm::engine::sprite::PickupKind;
+use wolf3d_wasm::engine::vectors::Vector3f;
+use wolf3d_wasm::graphics::screenshot::encode_png;
+
+use common::{load, map, root, HEIGHT, WIDTH};
+
+/// Largest channel difference for two pixels to be considered equal
+const CHANNEL_TOLERANCE: u8 = 16;
+/// Share of differing pixels allowed in a frame
+const PIXEL_TOLERANCE: f32 = 0.01;
+/// Key code of the Interact action
+const INTERACT: u32 = 70;
+/// Key code of the MoveForward action
+const MOVE_FORWARD: u32 = 87;
+
+fn render(game: &mut Game, x: f32, y: f32, z: f32, angle: f32) -> Vec<u8> {
+    game.set_camera(Vector3f::new(x, y, z), angle);
+    game.update(0.0);
+    game.frame().to_vec()
+}
+
+fn decode(bytes: &[u8]) -> Vec<u8> {
+    let decoder = png::Decoder::new(bytes);
+    let mut reader = decoder.read_info().unwrap();
+    let mut rgb = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut rgb).unwrap();
+
+    assert_eq!((info.width as usize, info.height as usize), (WIDTH, HEIGHT), "reference has the wrong size");
+    rgb.truncate(info.buffer_size());
+    rgb
+}
+
+fn check(case: &str, frame: &[u8]) {
+    let reference = root().join("tests/golden").join(format!("{}.png", case));
+
+    if std::env::var_os("GOLDEN_BLESS").is_some() {
+        fs::write(&reference, encode_png(frame, WIDTH, HEIGHT, 1.0).unwrap()).unwrap();
+        return;
+    }
+    let bytes = fs::read(&reference).unwrap_or_else(|err| panic!("{}: {}, run with GOLDEN_BLESS=1 to create it", reference.display(), err));
+    let expected = decode(&bytes);
+    let mut diff = Vec::with_capacity(frame.len());
+    let mut mismatches = 0;
+
+    for (actual, expected) in frame.chunks_exact(4).zip(expected.chunks_exact(3)) {
+        let differs = (0..3).any(|c| (actual[c] as i32 - expected[c] as i32).unsigned_abs() > CHANNEL_TOLERANCE as u32);
+
+        if differs {
+            mismatches += 1;
+            diff.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let gray = ((expected[0] as u32 + expected[1] as u32 + expected[2] as u32) / 6) as u8;
+            diff.extend_from_slice(&[gray, gray, gray, 255]);
+        }
+    }
+
+    let ratio = mismatches as f32 / (WIDTH * HEIGHT) as f32;
+    if ratio > PIXEL_TOLERANCE {
+        let out = root().join("target/golden");
+
+        fs::create_dir_all(&out).unwrap();
+        fs::write(out.join(format!("{}.actual.png", case)), encode_png(frame, WIDTH, HEIGHT, 1.0).unwrap()).unwrap();
+        fs::write(out.join(format!("{}.diff.png", case)), encode_png(&diff, WIDTH, HEIGHT, 1.0).unwrap()).unwrap();
+        panic!("{}: {} pixels differ ({:.2}%), see {}", case, mismatches, ratio * 100.0, out.display());
+    }
+}
+
+#[test]
+fn thin_walls() {
+    let mut game = load("thin_walls");
+
+    check("thin_walls_front", &render(&mut game, 1.5, 3.5, 0.0, 0.05));
+    check("thin_walls_angled", &render(&mut game, 1.5, 6.5, 0.0, -PI / 5.0));
+}
+
+#[test]
+fn door_mid_slide() {
+    let mut game = load("doors");
+
+    game.set_camera(Vector3f::new(3.2, 3.5, 0.0), 0.05);
+    game.process_event(INTERACT, true);
+    game.update(0.0);
+    game.process_event(INTERACT, false);
+    game.update(0.75);
+    check("door_mid_slide_front", &render(&mut game, 2.5, 3.5, 0.0, 0.05));
+    check("door_mid_slide_angled", &render(&mut game, 2.5, 1.5, 0.0, PI / 5.0));
+}
+
+#[test]
+fn half_walls() {
+    let mut game = load("half_walls");
+
+    check("half_walls_front", &render(&mut game, 1.5, 3.5, 0.0, 0.05));
+    check("half_walls_angled", &render(&mut game, 1.5, 6.5, 0.0, -PI / 6.0));
+}
+
+#[test]
+fn multi_floor() {
+    let mut game = load("multi_floor");
+
+    check("multi_floor_ledge", &render(&mut game, 1.5, 3.5, 1.0, 0.05));
+    check("multi_floor_ground", &render(&mut game, 6.5, 7.5, 0.0, -3.0 * PI / 4.0));
+}
+
+#[test]
+fn recursive_portals() {
+    let mut game = load("portals");
+
+    check("portals_front", &render(&mut game, 3.5, 3.5, 0.0, 0.05));
+    check("portals_angled", &render(&mut game, 2.5, 5.5, 0.0, -PI / 6.0));
+}
+
+#[test]
+fn floor_and_ceiling_textures() {
+    let mut game = load("surfaces");
+
+    check("surfaces_ground", &render(&mut game, 1.5, 1.5, 0.0, PI / 5.0));
+    check("surfaces_ledge", &render(&mut game, 6.5, 6.5, 1.0, -3.0 * PI / 4.0));
+}
+
+#[test]
+fn distance_fog() {
+    let mut game = load("fog");
+
+    check("fog_linear", &render(&mut game, 1.5, 2.6, 0.0, 0.05));
+    check("fog_exponential_black", &render(&mut game, 1.5, 2.6, 1.0, 0.05));
+}
+
+#[test]
+fn runtime_atlas() {
+    let mut atlas = TextureAtlas::new();
+    //32x32 checkerboard of 8 pixel squares
+    let checker: Vec<u8> = (0..32 * 32).flat_map(|i| if (i % 32 / 8 + i / 32 / 8) % 2 == 0 { [200, 40, 40, 255] } else { [240, 220, 200, 255] }).collect();
+    //16x128 vertical gradient with a transparent border, loaded from PNG
+    let gradient: Vec<u8> = (0..16 * 128).flat_map(|i| {
+        let (x, y) = (i % 16, i / 16);
+
+        if x == 0 || x == 15 { [0, 0, 0, 0] } else { [40, (y * 2) as u8, 255 - (y * 2) as u8, 255] }
+    }).collect();
+
+    atlas.add_rgba("checker", 32, 32, &checker).unwrap();
+    atlas.add_png("gradient", &encode_png(&gradient, 16, 128, 1.0).unwrap()).unwrap();
+    //Rebound, the darker copy of the gradient is replaced by the one of the checker
+    atlas.bind_wall(1, "gradient", None).unwrap();
+    atlas.bind_wall(1, "checker", None).unwrap();
+    atlas.bind_wall(2, "checker", None).unwrap();
+    atlas.bind_wall(2, "gradient", Some("checker")).unwrap();
+    //Values are not indexes, any of them may be bound
+    atlas.bind_wall(4_000_000_000, "checker", None).unwrap();
+    atlas.bind_sprite(1, "gradient").unwrap();
+
+    let mut game = Game::with_atlas(&map("atlas"), atlas, WIDTH, HEIGHT, Box::new(MemoryTarget)).unwrap();
+
+    check("atlas_front", &render(&mut game, 1.5, 3.5, 0.0, 0.05));
+    check("atlas_angled", &render(&mut game, 1.5, 1.5, 0.0, PI / 5.0));
+}
+
+#[test]
+fn enemies() {
+    let mut game = load("enemies");
+
+    check("enemies_noticed", &render(&mut game, 1.5, 3.5, 0.0, 0.05));
+    //Only the guard looking at the player notices it
+    assert_eq!(game.events(), &[Event::Alerted { enemy: 0 }]);
+    for _ in 0..30 {
+        game.update(1.0 / 30.0);
+    }
+    assert_eq!(game.enemies()[1].state, EnemyState::Idle);
+    assert_eq!(game.enemies()[3].state, EnemyState::Patrol);
+    check("enemies_chasing", &render(&mut game, 1.5, 3.5, 0.0, 0.05));
+}
+
+#[test]
+fn chase_through_portal() {
+    let mut game = load("chase");
+
+    render(&mut game, 5.5, 2.5, 0.0, 0.05);
+    assert_eq!(game.events(), &[Event::Alerted { enemy: 0 }]);
+    //Behind the wall, only reachable through the portal
+    let mut fired = false;
+
+    for _ in 0..90 {
+        game.set_camera(Vector3f::new(2.5, 2.5, 0.0), PI + 0.05);
+        game.update(1.0 / 30.0);
+        fired |= game.events().iter().any(|event| matches!(event, Event::Fired { .. }));
+    }
+    let enemy = &game.enemies()[0];
+
+    assert!(enemy.pos.x < 4.0, "enemy stayed at {:?}", enemy.pos);
+    assert!(fired, "enemy never fired from the other side");
+    check("chase_through_portal", &render(&mut game, 2.5, 2.5, 0.0, PI + 0.05));
+}
+
+#[test]
+fn death_and_respawn() {
+    let mut game = load("hazards");
+
+    render(&mut game, 2.5, 2.5, 0.0, 0.05);
+    assert_eq!(game.events(), &[Event::Checkpoint]);
+
+    //Standing in the hazard, 5 health lost every 0.1 second
+    game.set_camera(Vector3f::new(4.5, 2.5, 0.0), PI + 0.05);
+    let mut events = Vec::new();
+
+    for _ in 0..25 {
+        game.update(1.0 / 10.0);
+        events.extend_from_slice(game.events());
+    }
+    assert_eq!(events.iter().filter(|event| **event == Event::Hurt { damage: 5 }).count(), 19);
+    assert_eq!(events.iter().filter(|event| **event == Event::Died).count(), 1);
+    assert_eq!(game.status(), Status { health: 0, armor: 0, alive: false, weapon: Some("pistol".to_string()), ammo: Some(8), keys: Vec::new(), treasure: 0 });
+
+    //The camera lies on the ground and ignores the controls
+    game.process_event(MOVE_FORWARD, true);
+    game.update(1.0 / 10.0);
+    game.process_event(MOVE_FORWARD, false);
+    check("dead", game.frame());
+
+    game.process_event(INTERACT, true);
+    game.update(0.0);
+    assert_eq!(game.events(), &[Event::Respawned]);
+    assert_eq!(game.status().health, MAX_HEALTH);
+    check("respawned", game.frame());
+}
+
+#[test]
+fn pickups() {
+    let mut game = load("pickups");
+    let picked = |game: &mut Game, x: f32, y: f32, z: f32| {
+        render(game, x, y, z, 0.05);
+        game.events().to_vec()
+    };
+
+    check("pickups", &render(&mut game, 1.5, 1.5, 0.0, 0.05));
+    //Health is left for later at full health
+    assert_eq!(picked(&mut game, 2.5, 1.5, 0.0), []);
+    assert_eq!(picked(&mut game, 2.5, 3.5, 0.0), [Event::Picked { kind: PickupKind::Ammo, amount: 10, item: Some("bullets".to_string()) }]);
+    assert_eq!(picked(&mut game, 3.5, 1.5, 0.0), [Event::Picked { kind: PickupKind::Weapon, amount: 6, item: Some("machine_gun".to_string()) }]);
+    assert_eq!(picked(&mut game, 3.5, 3.5, 0.0), [Event::Picked { kind: PickupKind::Key, amount: 0, item: Some("gold".to_string()) }]);
+    //The treasure lies on the block, a floor above
+    assert_eq!(picked(&mut game, 4.5, 3.5, 0.0), []);
+    assert_eq!(picked(&mut game, 4.5, 2.5, 1.0), [Event::Picked { kind: PickupKind::Treasure, amount: 100, item: None }]);
+    assert_eq!(picked(&mut game, 5.5, 3.5, 0.0), [Event::Picked { kind: PickupKind::Armor, amount: 50, item: None }]);
+
+    let status = game.status();
+
+    assert_eq!((status.weapon.as_deref(), status.ammo, status.armor, status.treasure), (Some("machine_gun"), Some(24), 50, 100));
+    assert_eq!(status.keys, ["gold"]);
+    check("pickups_collected", &render(&mut game, 1.5, 1.5, 0.0, 0.05));
+}
+
+#[test]
+fn locked_door() {
+    let mut map = map("doors");
+    let door = map["cells"][0].as_array_mut().unwrap().iter_mut().find(|cell| cell["thin"] == true).unwrap();
+
+    door["lock"] = "gold".into();
+    map["sprites"].as_array_mut().unwrap().push(serde_json::json!({
+        "index": 3, "pos": {"x": 2, "y": 1, "z": 0}, "pickup": {"kind": "key", "item": "gold"}
+    }));
+    let mut game = Game::headless(&map, WIDTH, HEIGHT).unwrap();
+    let open = |game: &mut Game| {
+        game.set_camera(Vector3f::new(3.2, 3.5, 0.0), 0.05);
+        game.process_event(INTERACT, true);
+        game.update(0.0);
+        game.process_event(INTERACT, false);
+        let events = game.events().to_vec();
+
+        game.update(0.75);
+        events
+    };
+
+    assert_eq!(open(&mut game), [Event::Locked { key: "gold".to_string() }]);
+    check("door_locked", &render(&mut game, 2.5, 3.5, 0.0, 0.05));
+
+    render(&mut game, 2.5, 1.5, 0.0, 0.05);
+    assert_eq!(game.status().keys, ["gold"]);
+    assert_eq!(open(&mut game), []);
+    check("door_mid_slide_front", &render(&mut game, 2.5, 3.5, 0.0, 0.05));
+}
+
+#[test]
+fn solid_sprites() {
+    let mut map = map("doors");
+
+    //A pillar in front of a treasure, another one lying on the way around it
+    map["sprites"].as_array_mut().unwrap().extend([
+        serde_json::json!({"index": 3, "pos": {"x": 2, "y": 3, "z": 0}, "solid": true}),
+        serde_json::json!({"index": 3, "pos": {"x": 3, "y": 3, "z": 0}, "pickup": {"kind": "treasure", "amount": 100}}),
+        serde_json::json!({"index": 3, "pos": {"x": 3, "y": 2, "z": 0}, "pickup": {"kind": "treasure", "amount": 50}}),
+    ]);
+    let walk = |y: f32, angle: f32| {
+        let mut game = Game::headless(&map, WIDTH, HEIGHT).unwrap();
+        let mut events = Vec::new();
+
+        game.set_camera(Vector3f::new(1.5, y, 0.0), angle);
+        game.process_event(MOVE_FORWARD, true);
+        for _ in 0..20 {
+            game.update(1.0 / 20.0);
+            events.extend_from_slice(game.events());
+        }
+        game.process_event(MOVE_FORWARD, false);
+        (events, game.frame().to_vec())
+    };
+
+    let (events, frame) = walk(3.5, 0.0);
+
+    assert_eq!(events, []);
+    check("solid_blocked", &frame);
+    //Sliding along the pillar
+    let (events, _) = walk(3.4, -0.2);
+
+    assert_eq!(events, [Event::Picked { kind: PickupKind::Treasure, amount: 50, item: None }]);
+}
+
+#[test]
+fn pushwall() {
+    let mut game = load("pushwalls");
+    let push = |game: &mut Game| {
+        game.set_camera(Vector3f::new(2.2, 2.5, 0.0), 0.05);
+        game.process_event(INTERACT, true);
+        game.update(0.0);
+        game.process_event(INTERACT, false);
+        game.events().to_vec()
+    };
+
+    assert_eq!(push(&mut game), [Event::Secret]);
+    game.update(1.0);
+    check("pushwall_sliding", game.frame());
+    //Up to the wall at the end of the room
+    for _ in 0..10 {
+        game.update(1.0);
+    }
+    check("pushwall_stopped", game.frame());
+    assert_eq!(push(&mut game), []);
+}
+
+#[test]
+fn pushwall_distance() {
+    let mut map = map("pushwalls");
+    let wall = map["cells"][0].as_array_mut().unwrap().iter_mut().find(|cell| cell["pushable"] == true).unwrap();
+
+    wall["push_distance"] = 1.into();
+    let mut game = Game::headless(&map, WIDTH, HEIGHT).unwrap();
+
+    game.set_camera(Vector3f::new(2.2, 2.5, 0.0), 0.05);
+    game.process_event(INTERACT, true);
+    game.update(0.0);
+    game.process_event(INTERACT, false);
+    for _ in 0..10 {
+        game.update(1.0);
+    }
+    check("pushwall_distance", game.frame());
+}
+
+#[test]
+fn pushwall_held() {
+    let mut map = map("pushwalls");
+
+    //A barrel two cells behind the wall
+    map["sprites"] = serde_json::json!([{"index": 3, "pos": {"x": 5, "y": 2, "z": 0}, "solid": true}]);
+    let mut game = Game::headless(&map, WIDTH, HEIGHT).unwrap();
+
+    game.set_camera(Vector3f::new(2.2, 2.5, 0.0), 0.05);
+    game.process_event(INTERACT, true);
+    game.update(0.0);
+    game.process_event(INTERACT, false);
+    assert_eq!(game.events(), [Event::Secret]);
+    for _ in 0..10 {
+        game.update(1.0);
+    }
+    //Waiting in front of the barrel instead of walling it in
+    check("pushwall_held", game.frame());
+}
+
+#[test]
+fn strafing_release() {
+    //Releasing a key leaves the opposite one held
+    let mut game = load("pushwalls");
+
+    game.set_camera(Vector3f::new(1.5, 1.5, 0.0), 0.05);
+    game.process_key(&Key::from("KeyW"), true);
+    game.process_key(&Key::from("KeyS"), true);
+    game.process_key(&Key::from("KeyS"), false);
+    game.update(0.5);
+    check("strafing_forward", game.frame());
+}
+
+#[test]
+fn mouse_look() {
+    let mut game = load("pickups");
+
+    //Half a radian to the right, the view of the "pickups" reference
+    game.set_camera(Vector3f::new(1.5, 1.5, 0.0), 0.05 - 0.5);
+    game.process_mouse_move(200.0, 0.0);
+    game.update(0.0);
+    check("pickups", game.frame());
+
+    //Smoothed, the view catches up over time
+    game.set_mouse(MouseSettings::from_json(&serde_json::json!({"sensitivity": 0.005, "smoothing": 0.1, "invert_x": true})).unwrap());
+    game.set_camera(Vector3f::new(1.5, 1.5, 0.0), 0.05 - 0.5);
+    game.process_mouse_move(-100.0, 0.0);
+    game.update(0.0);
+    game.update(0.1);
+    let partial = game.frame().to_vec();
+
+    game.update(1.0);
+    assert_ne!(partial, game.frame());
+    check("pickups", game.frame());
+}
+
+#[test]
+fn pitch() {
+    let mut game = load("multi_floor");
+
+    //Looking up at the upper floor from the ground, down at the ground from the ledge
+    game.set_camera(Vector3f::new(6.5, 7.5, 0.0), -3.0 * PI / 4.0);
+    game.set_pitch(0.4);
+    game.update(0.0);
+    check("pitch_up", game.frame());
+    game.set_camera(Vector3f::new(1.5, 3.5, 1.0), 0.05);
+    game.set_pitch(-0.4);
+    game.update(0.0);
+    check("pitch_down", game.frame());
+
+    //The views behind the portals and the sprites move with the horizon
+    let mut game = load("portals");
+
+    game.set_camera(Vector3f::new(3.5, 3.5, 0.0), 0.05);
+    game.set_pitch(0.2);
+    game.update(0.0);
+    check("pitch_portals", game.frame());
+
+    let mut game = load("pickups");
+
+    game.set_camera(Vector3f::new(1.5, 1.5, 0.0), 0.05);
+    game.set_pitch(-0.3);
+    game.update(0.0);
+    check("pitch_sprites", game.frame());
+}
+
+#[test]
+fn gamepad_look() {
+    //Half a radian to the right, the view of the "pickups" reference
+    let mut game = load("pickups");
+
+    game.set_camera(Vector3f::new(1.5, 1.5, 0.0), 0.05 - 0.5);
+    game.process_axis(Axis::LookX, 1.0);
+    game.update(0.5 / 3.5);
+    game.process_axis(Axis::LookX, 0.0);
+    game.update(0.0);
+    check("pickups", game.frame());
+}
@@ -0,0 +1,168 @@
+//! Input checks
+//!
+//! Each case sets the controls up and checks the actions they trigger in a
+//! fixture map.
+
+mod common;
+
+use std::f32::consts::PI;
+
+use wolf3d_wasm::Game;
+use wolf3d_wasm::engine::{Action, Axis, Bindings, Event, GamepadSettings, Key, MouseSettings, MAX_PITCH};
+use wolf3d_wasm::engine::vectors::Vector3f;
+
+use common::{load, map, HEIGHT, WIDTH};
+
+/// Key code of the Interact action
+const INTERACT: u32 = 70;
+
+//Whether the player, steered by `input` from (1.5, 1.5) for some time, collects a marker where it is expected to stop
+fn walk(marker: (u32, u32), time: f32, input: impl FnOnce(&mut Game)) -> bool {
+    let mut map = map("pushwalls");
+
+    map["sprites"].as_array_mut().unwrap().push(serde_json::json!({
+        "index": 9, "pos": {"x": marker.0, "y": marker.1, "z": 0}, "pickup": {"kind": "treasure", "radius": 0.2}
+    }));
+    let mut game = Game::headless(&map, WIDTH, HEIGHT).unwrap();
+
+    game.set_camera(Vector3f::new(1.5, 1.5, 0.0), 0.05);
+    input(&mut game);
+    game.update(time);
+    !game.events().is_empty()
+}
+
+#[test]
+fn key_bindings() {
+    let mut game = load("pushwalls");
+    let mut bindings = Bindings::from_json(&game.bindings().to_json()).unwrap();
+
+    assert_eq!(&bindings, game.bindings());
+    assert_eq!(bindings.action(&Key::from("KeyW")), Some(Action::MoveForward));
+    assert_eq!(bindings.action(&Key::from(87)), Some(Action::MoveForward));
+    //Interact moved to E, several keys per action
+    bindings.bind(Action::Interact, Key::from("KeyE"));
+    bindings.unbind(&Key::from(INTERACT));
+    assert_eq!(bindings.keys(Action::Interact), [Key::from("KeyF"), Key::Button { button: 2 }, Key::from("KeyE")]);
+    game.set_bindings(Bindings::from_json(&bindings.to_json()).unwrap());
+
+    game.set_camera(Vector3f::new(2.2, 2.5, 0.0), 0.05);
+    game.process_event(INTERACT, true);
+    game.update(0.0);
+    assert_eq!(game.events(), []);
+    game.process_key(&Key::from("KeyE"), true);
+    game.update(0.0);
+    assert_eq!(game.events(), [Event::Secret]);
+
+    let err = Bindings::from_json(&serde_json::json!({"jump": ["Space"], "fire": ["ControlLeft", "Space"]})).err().unwrap();
+
+    assert_eq!(err.to_string(), "fire[1]: the key is already bound to jump");
+}
+
+#[test]
+fn strafing() {
+    let keys = |keys: &'static [&'static str]| move |game: &mut Game| {
+        for key in keys {
+            game.process_key(&Key::from(*key), true);
+        }
+    };
+
+    assert!(walk((1, 3), 0.5, keys(&["KeyE"])));
+    //Diagonals go as fast as straight lines
+    assert!(walk((2, 2), 2.0f32.sqrt() / 4.0, keys(&["KeyW", "KeyE"])));
+    //Opposite keys cancel each other
+    assert!(walk((1, 1), 0.5, keys(&["KeyW", "KeyS"])));
+}
+
+#[test]
+fn legacy_codes() {
+    let bindings = Bindings::default();
+
+    //Q strafes and A looks left, as in the first versions
+    assert_eq!(bindings.action(&Key::from(81)), Some(Action::StrafeLeft));
+    assert_eq!(bindings.action(&Key::from(65)), Some(Action::LookLeft));
+    assert_eq!(bindings.action(&Key::from(90)), Some(Action::MoveForward));
+}
+
+#[test]
+fn mouse_settings() {
+    let mouse = MouseSettings::from_json(&serde_json::json!({"sensitivity": 0.005, "invert_x": true})).unwrap();
+
+    assert_eq!((mouse.sensitivity, mouse.smoothing, mouse.invert_x, mouse.invert_y), (0.005, 0.0, true, false));
+    let err = MouseSettings::from_json(&serde_json::json!({"smoothing": -1.0})).err().unwrap();
+
+    assert_eq!(err.to_string(), "smoothing: the smoothing must not be negative");
+}
+
+#[test]
+fn pitch_controls() {
+    let mut game = load("pickups");
+
+    //Moving the mouse down looks down, unless inverted, as far as the limit
+    game.set_camera(Vector3f::new(1.5, 1.5, 0.0), 0.05);
+    game.process_mouse_move(0.0, 40.0);
+    game.update(0.0);
+    assert!((game.pitch() + 0.1).abs() < 1e-5);
+    game.set_mouse(MouseSettings::from_json(&serde_json::json!({"invert_y": true})).unwrap());
+    game.process_mouse_move(0.0, 1000.0);
+    game.update(0.0);
+    assert_eq!(game.pitch(), MAX_PITCH);
+
+    //Keys look up and down over time, and a new pose looks at the horizon
+    game.process_action(Action::LookDown, true);
+    game.update(0.1);
+    assert!(game.pitch() < MAX_PITCH);
+    game.process_action(Action::LookDown, false);
+    game.set_camera(Vector3f::new(1.5, 1.5, 0.0), 0.05);
+    assert_eq!(game.pitch(), 0.0);
+    game.set_pitch(-PI);
+    assert_eq!(game.pitch(), -MAX_PITCH);
+}
+
+#[test]
+fn gamepad() {
+    let stick = |settings: serde_json::Value, x: f32, y: f32| move |game: &mut Game| {
+        game.set_gamepad(GamepadSettings::from_json(&settings).unwrap());
+        game.process_axis(Axis::MoveX, x);
+        game.process_axis(Axis::MoveY, y);
+    };
+
+    //Pushing the stick up walks forward, as fast as the keys
+    assert!(walk((3, 1), 0.5, stick(serde_json::json!({}), 0.0, -1.0)));
+    //Half pushed, linearly, half as fast
+    assert!(walk((3, 1), 1.0, stick(serde_json::json!({"dead_zone": 0, "curve": 1}), 0.0, -0.5)));
+    assert!(!walk((3, 1), 0.5, stick(serde_json::json!({"dead_zone": 0, "curve": 1}), 0.0, -0.5)));
+    //Quadratic past the dead zone
+    assert!(walk((3, 1), 2.0, stick(serde_json::json!({"dead_zone": 0.2, "curve": 2}), 0.0, -0.6)));
+    //A stick at rest is not perfectly centered
+    assert!(walk((1, 1), 0.5, stick(serde_json::json!({}), 0.1, -0.1)));
+
+    //Pushing the looking stick up looks up, unless inverted
+    let mut game = load("pickups");
+
+    game.set_camera(Vector3f::new(1.5, 1.5, 0.0), 0.05);
+    game.process_axis(Axis::LookY, -1.0);
+    game.update(0.1);
+    assert!(game.pitch() > 0.0);
+    game.set_gamepad(GamepadSettings::from_json(&serde_json::json!({"invert_y": true})).unwrap());
+    game.update(0.2);
+    assert!(game.pitch() < 0.0);
+
+    //Buttons trigger their action once while held, and are rebound like keys
+    let mut game = load("pushwalls");
+    let mut bindings = game.bindings().clone();
+
+    assert_eq!(bindings.action(&Key::Button { button: 2 }), Some(Action::Interact));
+    bindings.bind(Action::Interact, Key::Button { button: 1 });
+    game.set_bindings(Bindings::from_json(&bindings.to_json()).unwrap());
+    game.set_camera(Vector3f::new(2.2, 2.5, 0.0), 0.05);
+    game.process_button(1, true);
+    game.update(0.0);
+    assert_eq!(game.events(), [Event::Secret]);
+    game.process_button(1, true);
+    game.update(0.0);
+    assert_eq!(game.events(), []);
+
+    let err = GamepadSettings::from_json(&serde_json::json!({"dead_zone": 1})).err().unwrap();
+
+    assert_eq!(err.to_string(), "dead_zone: the dead zone must lie between 0 and 1");
+}
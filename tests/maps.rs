@@ -0,0 +1,78 @@
+//! Map loading checks
+//!
+//! Each case describes a map and checks that the game rejects it with the
+//! expected error, or accepts it.
+
+mod common;
+
+use wolf3d_wasm::{Game, MemoryTarget};
+use wolf3d_wasm::engine::MAX_MAP_CELLS;
+use wolf3d_wasm::graphics::TextureAtlas;
+
+use common::{map, HEIGHT, WIDTH};
+
+#[test]
+fn oversized_map() {
+    let error = |width: usize, height: usize, floors: usize| {
+        let map = serde_json::json!({"width": width, "height": height, "floors": floors, "cells": []});
+
+        Game::headless(&map, WIDTH, HEIGHT).err().unwrap().to_string()
+    };
+    let message = format!("the map holds more than {} cells", MAX_MAP_CELLS);
+
+    assert_eq!(error(usize::MAX, 2, 1), format!("width: {}", message));
+    assert_eq!(error(1 << 20, 1 << 20, 1), format!("height: {}", message));
+    assert_eq!(error(1024, 1024, 1 << 20), format!("floors: {}", message));
+    assert!(Game::headless(&serde_json::json!({"width": 64, "height": 64, "floors": 4, "cells": []}), WIDTH, HEIGHT).is_ok());
+}
+
+#[test]
+fn unbound_texture() {
+    let err = Game::with_atlas(&map("atlas"), TextureAtlas::new(), WIDTH, HEIGHT, Box::new(MemoryTarget)).err().unwrap();
+
+    assert_eq!(err.to_string(), "surfaces[0].floor: no texture is bound to 2");
+}
+
+#[test]
+fn unknown_weapon_pickup() {
+    let mut map = map("pickups");
+
+    map["sprites"][3]["pickup"]["item"] = "rocket_launcher".into();
+    let err = Game::headless(&map, WIDTH, HEIGHT).err().unwrap();
+
+    assert_eq!(err.to_string(), "sprites[3].pickup.item: unknown weapon \"rocket_launcher\"");
+}
+
+#[test]
+fn locked_wall() {
+    let mut map = map("doors");
+
+    map["cells"][0][0]["lock"] = "gold".into();
+    let err = Game::headless(&map, WIDTH, HEIGHT).err().unwrap();
+
+    assert_eq!(err.to_string(), "cells[0][0].lock: only doors can be locked");
+}
+
+#[test]
+fn solid_radius() {
+    let mut map = map("doors");
+
+    map["sprites"][0]["radius"] = (-1.0).into();
+    let err = Game::headless(&map, WIDTH, HEIGHT).err().unwrap();
+
+    assert_eq!(err.to_string(), "sprites[0].radius: the radius must be positive");
+}
+
+#[test]
+fn campaign_error() {
+    let mut second = map("pushwalls");
+
+    second["cells"][0][0]["value"] = 999.into();
+    let campaign = serde_json::json!({"maps": [map("pushwalls"), second]});
+    let err = Game::with_campaign(&campaign, TextureAtlas::builtin(), WIDTH, HEIGHT, Box::new(MemoryTarget)).err().unwrap();
+
+    assert_eq!(err.to_string(), "maps[1].cells[0][0].value: no texture is bound to 999");
+    let err = Game::with_campaign(&serde_json::json!({"maps": []}), TextureAtlas::builtin(), WIDTH, HEIGHT, Box::new(MemoryTarget)).err().unwrap();
+
+    assert_eq!(err.to_string(), "maps: a campaign needs a map");
+}
@@ -0,0 +1,32 @@
+//! Path finding checks
+//!
+//! Each case searches paths across a fixture map and compares them with the
+//! expected waypoints.
+
+mod common;
+
+use wolf3d_wasm::engine::Map;
+use wolf3d_wasm::engine::navigation::{find_path, NavOptions, Waypoint};
+use wolf3d_wasm::engine::vectors::Vector3f;
+use wolf3d_wasm::graphics::TextureAtlas;
+
+use common::map;
+
+#[test]
+fn navigation() {
+    let options = NavOptions::default();
+    let portals = Map::new(&serde_json::from_value(map("portals")).unwrap(), &TextureAtlas::builtin()).unwrap();
+    let path = find_path(&portals, Vector3f::new(5.5, 3.5, 0.0), Vector3f::new(1.5, 3.5, 0.0), &options).unwrap();
+
+    assert_eq!(path, [
+        Waypoint { pos: Vector3f::new(6.5, 3.5, 0.0), portal: true },
+        Waypoint { pos: Vector3f::new(1.5, 3.5, 0.0), portal: false },
+    ]);
+
+    //Dropping from the ledge, but too high to climb back
+    let floors = Map::new(&serde_json::from_value(map("multi_floor")).unwrap(), &TextureAtlas::builtin()).unwrap();
+    let (ledge, ground) = (Vector3f::new(1.5, 3.5, 1.0), Vector3f::new(6.5, 6.5, 0.0));
+
+    assert_eq!(find_path(&floors, ledge, ground, &options).unwrap().last().unwrap().pos, ground);
+    assert_eq!(find_path(&floors, ground, ledge, &options), None);
+}
@@ -0,0 +1,37 @@
+//! Screenshot checks
+//!
+//! Each case encodes the frame of a small map and checks that the scale and
+//! the size of the image are accepted, or rejected with the expected error.
+
+use wolf3d_wasm::Game;
+use wolf3d_wasm::graphics::screenshot::MAX_SCALE;
+
+fn room(width: usize, height: usize) -> Game {
+    let map = serde_json::json!({"width": 3, "height": 3, "floors": 1, "cells": [[{"pos": {"x": 0, "y": 0}, "value": 1, "height": 1.0}]]});
+    let mut game = Game::headless(&map, width, height).unwrap();
+
+    game.update(0.0);
+    game
+}
+
+#[test]
+fn screenshot_scale() {
+    let game = room(128, 96);
+
+    for scale in [0.25, 1.0, MAX_SCALE] {
+        assert!(game.screenshot(scale).is_ok());
+    }
+    for scale in [0.0, -1.0, f32::NAN, f32::INFINITY, MAX_SCALE * 2.0] {
+        assert!(game.screenshot(scale).is_err(), "scale {}", scale);
+    }
+    assert_eq!(game.screenshot(1e9).err().unwrap().to_string(), "invalid screenshot scale 1000000000, expected a positive number up to 8");
+}
+
+#[test]
+fn empty_screenshot() {
+    let game = room(0, 0);
+
+    for scale in [0.25, 1.0, MAX_SCALE] {
+        assert_eq!(game.screenshot(scale).err().unwrap().to_string(), "the image is empty");
+    }
+}